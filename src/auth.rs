@@ -0,0 +1,48 @@
+use crate::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+type TokenFuture = Pin<Box<dyn Future<Output = Result<String, Error>> + Send>>;
+
+/// Obtains bearer tokens from a user-provided callback, e.g. to support credentials
+/// that rotate frequently. Tokens are reused for the time-to-live, if any.
+pub(crate) struct TokenProvider {
+    fetch: Box<dyn Fn() -> TokenFuture + Send + Sync>,
+    ttl: Option<Duration>,
+    current: Mutex<Option<(Instant, String)>>,
+}
+
+impl TokenProvider {
+    pub(crate) fn new<F, Fut>(provider: F, ttl: Option<Duration>) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, Error>> + Send + 'static,
+    {
+        TokenProvider {
+            fetch: Box::new(move || Box::pin(provider())),
+            ttl,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Return the current token if it is still valid, or fetch a new one.
+    pub(crate) async fn token(&self) -> Result<String, Error> {
+        if let Some(ttl) = self.ttl {
+            let current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((fetched, token)) = current.as_ref() {
+                if fetched.elapsed() < ttl {
+                    return Ok(token.clone());
+                }
+            }
+        }
+
+        let token = (self.fetch)().await?;
+        if self.ttl.is_some() {
+            let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+            *current = Some((Instant::now(), token.clone()));
+        }
+        Ok(token)
+    }
+}
@@ -0,0 +1,52 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Entry = (Instant, Arc<dyn Any + Send + Sync>);
+
+/// A simple in-memory cache for parsed metadata responses that is shared
+/// among all clones of a [`Client`](crate::Client).
+pub(crate) struct Cache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl Cache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Cache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Build the cache key of a request from its path and parameters.
+    pub(crate) fn key(path: &str, params: &[(&str, String)]) -> String {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+        format!("{}?{}", path, query)
+    }
+
+    /// Return a copy of the cached value if it has not expired yet.
+    pub(crate) fn get<T: Clone + 'static>(&self, key: &str) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some((inserted, value)) if inserted.elapsed() < self.ttl => {
+                value.downcast_ref::<T>().cloned()
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store the value, also removing all expired entries in the process.
+    pub(crate) fn insert<T: Send + Sync + 'static>(&self, key: String, value: T) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+        entries.insert(key, (Instant::now(), Arc::new(value)));
+    }
+}
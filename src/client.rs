@@ -1,21 +1,61 @@
-use crate::error::{ClientError, Error};
+use crate::auth::TokenProvider;
+#[cfg(feature = "cache")]
+use crate::cache::Cache;
+use crate::error::{ClientError, Error, TooManyPointsError};
 use crate::response::*;
 use crate::selector::Selector;
 use crate::util::{self, build_final_url, RuleKind, TargetState, ToBaseUrl};
-use reqwest::header::{HeaderMap, HeaderValue, IntoHeaderName, CONTENT_TYPE};
-use reqwest::Method as HttpMethod;
-use serde::{de::DeserializeOwned, Serialize};
-use std::borrow::Borrow;
+use reqwest::header::{
+    HeaderMap, HeaderValue, IntoHeaderName, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER,
+};
+use reqwest::{Method as HttpMethod, StatusCode};
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize, Serialize,
+};
+use std::borrow::{Borrow, Cow};
 use std::collections::HashMap;
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::time::Duration;
 use url::Url;
 
+/// Additional time granted to the HTTP request on top of a query deadline, see
+/// [`InstantQueryBuilder::deadline`] and [`RangeQueryBuilder::deadline`].
+const DEADLINE_SLACK: Duration = Duration::from_secs(1);
+
+/// Maximum number of sub-ranges that [`Client::query_range_chunked`] queries at the same time.
+const MAX_CONCURRENT_CHUNKS: usize = 4;
+
+/// Default upper bound for waiting as demanded by a `Retry-After` header, see [`Client::retry`].
+const DEFAULT_MAX_RETRY_WAIT: Duration = Duration::from_secs(60);
+
+/// Check the time range and step width of a range query.
+fn validate_range(start: i64, end: i64, step: f64) -> Result<(), Error> {
+    if start > end {
+        return Err(Error::InvalidTimeRange);
+    }
+    if !step.is_finite() || step <= 0.0 {
+        return Err(Error::InvalidStep);
+    }
+    Ok(())
+}
+
+/// Set the query parameter `name` to `value`, replacing all previous values of the parameter.
+fn set_param(params: &mut Vec<(Cow<'static, str>, String)>, name: &'static str, value: String) {
+    params.retain(|(n, _)| n != name);
+    params.push((Cow::Borrowed(name), value));
+}
+
 /// Provides a builder to set some query parameters in the context
 /// of an instant query before sending it to Prometheus.
 #[derive(Clone)]
 pub struct InstantQueryBuilder {
     client: Client,
-    params: Vec<(&'static str, String)>,
+    params: Vec<(Cow<'static, str>, String)>,
     headers: Option<HeaderMap<HeaderValue>>,
+    http_timeout: Option<Duration>,
+    time: Option<i64>,
 }
 
 impl InstantQueryBuilder {
@@ -24,21 +64,42 @@ impl InstantQueryBuilder {
     /// server time.
     /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#instant-queries)
     pub fn at(mut self, time: i64) -> Self {
-        self.params.push(("time", time.to_string()));
+        self.params.push((Cow::Borrowed("time"), time.to_string()));
+        self.time = Some(time);
         self
     }
 
     /// Set the evaluation timeout (milliseconds, e.g. 1000).
     /// If this is not set the timeout will default to the value of the "-query.timeout" flag of the Prometheus server.
+    /// This replaces a timeout or deadline that was set before, i.e. the query is no longer
+    /// aborted locally after a previously set deadline.
     /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#instant-queries)
     pub fn timeout(mut self, timeout: i64) -> Self {
-        self.params.push(("timeout", format!("{}ms", timeout)));
+        set_param(&mut self.params, "timeout", format!("{}ms", timeout));
+        self.http_timeout = None;
+        self
+    }
+
+    /// Set a deadline for the query. This sets the evaluation timeout (see `timeout`)
+    /// to the given duration and additionally aborts the HTTP request locally if the
+    /// server did not respond within the deadline plus one second of slack, e.g. when
+    /// the server or an intermediate proxy ignores the evaluation timeout.
+    /// This replaces a timeout or deadline that was set before.
+    /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#instant-queries)
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        set_param(
+            &mut self.params,
+            "timeout",
+            format!("{}ms", deadline.as_millis()),
+        );
+        self.http_timeout = Some(deadline + DEADLINE_SLACK);
         self
     }
 
     /// Instruct Prometheus to compile query statistics as part of the API response.
     pub fn stats(mut self) -> Self {
-        self.params.push(("stats", String::from("all")));
+        self.params
+            .push((Cow::Borrowed("stats"), String::from("all")));
         self
     }
 
@@ -50,16 +111,75 @@ impl InstantQueryBuilder {
         self
     }
 
-    /// Include an additional parameter to the request.
+    /// Set a header that e.g. routes this query to a specific backend behind a query
+    /// frontend, like `X-Prometheus-Backend`. Unlike [`header`](Self::header) this replaces
+    /// all previous values of the header, including a default header set via
+    /// [`ClientBuilder::default_headers`].
+    pub fn route_header<K: IntoHeaderName, T: Into<HeaderValue>>(
+        mut self,
+        name: K,
+        value: T,
+    ) -> Self {
+        self.headers
+            .get_or_insert_with(Default::default)
+            .insert(name, value.into());
+        self
+    }
+
+    /// Include an additional parameter to the request, e.g. a parameter that was added
+    /// by a newer version of Prometheus or a compatible backend like Thanos and that is not
+    /// modelled by this builder yet. The parameter is sent as-is without any validation,
+    /// as query string parameter (GET) or form field (POST). Calling this method repeatedly
+    /// with the same name sends the parameter repeatedly. Use [`Self::extra_param`] if the
+    /// name of the parameter is not known at compile time.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// let url = Client::default()
+    ///     .query("up")
+    ///     .query("dedup", false)
+    ///     .query("replicaLabels[]", "replica")
+    ///     .build_url()
+    ///     .unwrap();
+    ///
+    /// assert!(url.as_str().ends_with("&dedup=false&replicaLabels%5B%5D=replica"));
+    /// ```
     pub fn query(mut self, name: &'static str, value: impl ToString) -> Self {
-        self.params.push((name, value.to_string()));
+        self.params.push((Cow::Borrowed(name), value.to_string()));
+        self
+    }
+
+    /// Include an additional parameter to the request like [`Self::query`] does, but with a
+    /// name that is only known at runtime, e.g. because it is read from a configuration file.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// let name = format!("{}[]", "replicaLabels");
+    ///
+    /// let url = Client::default()
+    ///     .query("up")
+    ///     .extra_param(name, "replica")
+    ///     .build_url()
+    ///     .unwrap();
+    ///
+    /// assert!(url.as_str().ends_with("&replicaLabels%5B%5D=replica"));
+    /// ```
+    pub fn extra_param(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.params
+            .push((Cow::Owned(name.into()), value.to_string()));
         self
     }
 
     /// Execute the instant query (using HTTP GET) and return the parsed API response.
     pub async fn get(self) -> Result<PromqlResult, Error> {
+        let time = self.time;
+        let client = self.client.clone();
         let response = self.get_raw().await?;
-        Client::deserialize(response).await
+        let mut result: PromqlResult = client.deserialize(response).await?;
+        result.evaluation_time = time;
+        Ok(result)
     }
 
     /// Execute the instant query (using HTTP POST) and return the parsed API response.
@@ -67,14 +187,25 @@ impl InstantQueryBuilder {
     /// the size of the final URL may break Prometheus' or an intermediate proxies' URL
     /// character limits.
     pub async fn post(self) -> Result<PromqlResult, Error> {
+        let time = self.time;
+        let client = self.client.clone();
         let response = self.post_raw().await?;
-        Client::deserialize(response).await
+        let mut result: PromqlResult = client.deserialize(response).await?;
+        result.evaluation_time = time;
+        Ok(result)
+    }
+
+    /// Return the final URL (including the query string) that is used when
+    /// executing the instant query using HTTP GET, without sending the request.
+    /// This is useful for debugging purposes, e.g. to open the URL in a browser.
+    pub fn build_url(&self) -> Result<Url, Error> {
+        self.client.build_url("api/v1/query", &self.params)
     }
 
     /// Execute the instant query (using HTTP GET) and return the raw API response.
     pub async fn get_raw(self) -> Result<reqwest::Response, Error> {
         self.client
-            .send("api/v1/query", &self.params, HttpMethod::GET, self.headers)
+            .execute("api/v1/query", self.request(HttpMethod::GET))
             .await
     }
 
@@ -84,9 +215,20 @@ impl InstantQueryBuilder {
     /// character limits.
     pub async fn post_raw(self) -> Result<reqwest::Response, Error> {
         self.client
-            .send("api/v1/query", &self.params, HttpMethod::POST, self.headers)
+            .execute("api/v1/query", self.request(HttpMethod::POST))
             .await
     }
+
+    /// Build the final HTTP request without sending it.
+    fn request(&self, method: HttpMethod) -> reqwest::RequestBuilder {
+        let request =
+            self.client
+                .request("api/v1/query", &self.params, method, self.headers.clone());
+        match self.http_timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
 }
 
 /// Provides a builder to set some query parameters in the context
@@ -94,22 +236,55 @@ impl InstantQueryBuilder {
 #[derive(Clone)]
 pub struct RangeQueryBuilder {
     client: Client,
-    params: Vec<(&'static str, String)>,
+    params: Vec<(Cow<'static, str>, String)>,
     headers: Option<HeaderMap<HeaderValue>>,
+    http_timeout: Option<Duration>,
+    start: i64,
+    end: i64,
+    step: f64,
+    max_points: Option<u64>,
 }
 
 impl RangeQueryBuilder {
     /// Set the evaluation timeout (milliseconds, e.g. 1000).
     /// If this is not set the timeout will default to the value of the "-query.timeout" flag of the Prometheus server.
+    /// This replaces a timeout or deadline that was set before, i.e. the query is no longer
+    /// aborted locally after a previously set deadline.
     /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#range-queries)
     pub fn timeout(mut self, timeout: i64) -> Self {
-        self.params.push(("timeout", format!("{}ms", timeout)));
+        set_param(&mut self.params, "timeout", format!("{}ms", timeout));
+        self.http_timeout = None;
+        self
+    }
+
+    /// Set a deadline for the query. This sets the evaluation timeout (see `timeout`)
+    /// to the given duration and additionally aborts the HTTP request locally if the
+    /// server did not respond within the deadline plus one second of slack, e.g. when
+    /// the server or an intermediate proxy ignores the evaluation timeout.
+    /// This replaces a timeout or deadline that was set before.
+    /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#range-queries)
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        set_param(
+            &mut self.params,
+            "timeout",
+            format!("{}ms", deadline.as_millis()),
+        );
+        self.http_timeout = Some(deadline + DEADLINE_SLACK);
+        self
+    }
+
+    /// Fail with [`Error::TooManyPoints`] before sending the query if it would return more than
+    /// `max` data points per time series, i.e. if `(end - start) / step + 1` exceeds `max`.
+    /// Prometheus itself rejects range queries with more than 11,000 points per time series.
+    pub fn max_points_guard(mut self, max: u64) -> Self {
+        self.max_points = Some(max);
         self
     }
 
     /// Instruct Prometheus to compile query statistics as part of the API response.
     pub fn stats(mut self) -> Self {
-        self.params.push(("stats", String::from("all")));
+        self.params
+            .push((Cow::Borrowed("stats"), String::from("all")));
         self
     }
 
@@ -121,16 +296,72 @@ impl RangeQueryBuilder {
         self
     }
 
-    /// Include an additional parameter to the request.
+    /// Set a header that e.g. routes this query to a specific backend behind a query
+    /// frontend, like `X-Prometheus-Backend`. Unlike [`header`](Self::header) this replaces
+    /// all previous values of the header, including a default header set via
+    /// [`ClientBuilder::default_headers`].
+    pub fn route_header<K: IntoHeaderName, T: Into<HeaderValue>>(
+        mut self,
+        name: K,
+        value: T,
+    ) -> Self {
+        self.headers
+            .get_or_insert_with(Default::default)
+            .insert(name, value.into());
+        self
+    }
+
+    /// Include an additional parameter to the request, e.g. a parameter that was added
+    /// by a newer version of Prometheus or a compatible backend like Thanos and that is not
+    /// modelled by this builder yet. The parameter is sent as-is without any validation,
+    /// as query string parameter (GET) or form field (POST). Calling this method repeatedly
+    /// with the same name sends the parameter repeatedly. Use [`Self::extra_param`] if the
+    /// name of the parameter is not known at compile time.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// let url = Client::default()
+    ///     .query_range("up", 1648373100, 1648373300, 10.0)
+    ///     .query("dedup", false)
+    ///     .query("replicaLabels[]", "replica")
+    ///     .build_url()
+    ///     .unwrap();
+    ///
+    /// assert!(url.as_str().ends_with("&dedup=false&replicaLabels%5B%5D=replica"));
+    /// ```
     pub fn query(mut self, name: &'static str, value: impl ToString) -> Self {
-        self.params.push((name, value.to_string()));
+        self.params.push((Cow::Borrowed(name), value.to_string()));
+        self
+    }
+
+    /// Include an additional parameter to the request like [`Self::query`] does, but with a
+    /// name that is only known at runtime, e.g. because it is read from a configuration file.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// let name = format!("{}[]", "replicaLabels");
+    ///
+    /// let url = Client::default()
+    ///     .query_range("up", 1648373100, 1648373300, 10.0)
+    ///     .extra_param(name, "replica")
+    ///     .build_url()
+    ///     .unwrap();
+    ///
+    /// assert!(url.as_str().ends_with("&replicaLabels%5B%5D=replica"));
+    /// ```
+    pub fn extra_param(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.params
+            .push((Cow::Owned(name.into()), value.to_string()));
         self
     }
 
     /// Execute the range query (using HTTP GET) and return the parsed API response.
     pub async fn get(self) -> Result<PromqlResult, Error> {
+        let client = self.client.clone();
         let response = self.get_raw().await?;
-        Client::deserialize(response).await
+        client.deserialize(response).await
     }
 
     /// Execute the instant query (using HTTP POST) and return the parsed API response.
@@ -138,19 +369,24 @@ impl RangeQueryBuilder {
     /// the size of the final URL may break Prometheus' or an intermediate proxies' URL
     /// character limits.
     pub async fn post(self) -> Result<PromqlResult, Error> {
+        let client = self.client.clone();
         let response = self.post_raw().await?;
-        Client::deserialize(response).await
+        client.deserialize(response).await
+    }
+
+    /// Return the final URL (including the query string) that is used when
+    /// executing the range query using HTTP GET, without sending the request.
+    /// This is useful for debugging purposes, e.g. to open the URL in a browser.
+    pub fn build_url(&self) -> Result<Url, Error> {
+        self.validate()?;
+        self.client.build_url("api/v1/query_range", &self.params)
     }
 
     /// Execute the range query (using HTTP GET) and return the raw API response.
     pub async fn get_raw(self) -> Result<reqwest::Response, Error> {
+        self.validate()?;
         self.client
-            .send(
-                "api/v1/query_range",
-                &self.params,
-                HttpMethod::GET,
-                self.headers,
-            )
+            .execute("api/v1/query_range", self.request(HttpMethod::GET))
             .await
     }
 
@@ -159,15 +395,37 @@ impl RangeQueryBuilder {
     /// the size of the final URL may break Prometheus' or an intermediate proxies' URL
     /// character limits.
     pub async fn post_raw(self) -> Result<reqwest::Response, Error> {
+        self.validate()?;
         self.client
-            .send(
-                "api/v1/query_range",
-                &self.params,
-                HttpMethod::POST,
-                self.headers,
-            )
+            .execute("api/v1/query_range", self.request(HttpMethod::POST))
             .await
     }
+
+    /// Reject time ranges and step widths that Prometheus would refuse anyway.
+    fn validate(&self) -> Result<(), Error> {
+        validate_range(self.start, self.end, self.step)?;
+        if let Some(max) = self.max_points {
+            let requested = ((self.end - self.start) as f64 / self.step).floor() as u64 + 1;
+            if requested > max {
+                return Err(Error::TooManyPoints(TooManyPointsError { requested, max }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the final HTTP request without sending it.
+    fn request(&self, method: HttpMethod) -> reqwest::RequestBuilder {
+        let request = self.client.request(
+            "api/v1/query_range",
+            &self.params,
+            method,
+            self.headers.clone(),
+        );
+        match self.http_timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
 }
 
 /// Provides methods to build a query to the rules endpoint and send it to Prometheus.
@@ -257,8 +515,10 @@ impl RulesQueryBuilder {
     /// Execute the rules query (using HTTP GET) and return the [`RuleGroup`]s sent
     /// by Prometheus.
     pub async fn get(self) -> Result<Vec<RuleGroup>, Error> {
+        let client = self.client.clone();
         let response = self.get_raw().await?;
-        Client::deserialize(response)
+        client
+            .deserialize(response)
             .await
             .map(|r: RuleGroups| r.groups)
     }
@@ -329,8 +589,9 @@ impl<'a> TargetMetadataQueryBuilder<'a> {
     /// Execute the target metadata query (using HTTP GET) and return the collection of
     /// [`TargetMetadata`] sent by Prometheus.
     pub async fn get(self) -> Result<Vec<TargetMetadata>, Error> {
+        let client = self.client.clone();
         let response = self.get_raw().await?;
-        Client::deserialize(response).await
+        client.deserialize(response).await
     }
 
     /// Execute the target metadata query (using HTTP GET) and return the raw response
@@ -394,8 +655,9 @@ impl MetricMetadataQueryBuilder {
     /// Execute the metric metadata query (using HTTP GET) and return the collection of
     /// [`MetricMetadata`] sent by Prometheus.
     pub async fn get(self) -> Result<HashMap<String, Vec<MetricMetadata>>, Error> {
+        let client = self.client.clone();
         let response = self.get_raw().await?;
-        Client::deserialize(response).await
+        client.deserialize(response).await
     }
 
     /// Execute the metric metadata query (using HTTP GET) and return the raw response
@@ -449,14 +711,32 @@ impl SeriesQueryBuilder {
 
     /// Execute the series metadata query (using HTTP GET) and return a collection of
     /// matching time series sent by Prometheus.
-    pub async fn get(self) -> Result<Vec<HashMap<String, String>>, Error> {
-        let response = self.get_raw().await?;
-        Client::deserialize(response).await
+    pub async fn get(self) -> Result<Vec<Metric>, Error> {
+        let path = "api/v1/series";
+        let params = self.params();
+
+        if let Some(cached) = self.client.cached(path, &params) {
+            return Ok(cached);
+        }
+
+        let response = self
+            .client
+            .send(path, &params, HttpMethod::GET, None)
+            .await?;
+        let result: Vec<Metric> = self.client.deserialize(response).await?;
+        self.client.cache(path, &params, &result);
+        Ok(result)
     }
 
     /// Execute the series metadata query (using HTTP GET) and return the raw response
     /// sent by Prometheus.
     pub async fn get_raw(self) -> Result<reqwest::Response, Error> {
+        self.client
+            .send("api/v1/series", &self.params(), HttpMethod::GET, None)
+            .await
+    }
+
+    fn params(&self) -> Vec<(&'static str, String)> {
         let mut params = vec![];
 
         if let Some(start) = self.start {
@@ -467,11 +747,8 @@ impl SeriesQueryBuilder {
             params.push(("end", end.to_string()));
         }
 
-        params.extend(self.selectors);
-
-        self.client
-            .send("api/v1/series", &params, HttpMethod::GET, None)
-            .await
+        params.extend(self.selectors.iter().cloned());
+        params
     }
 }
 
@@ -521,12 +798,30 @@ impl LabelNamesQueryBuilder {
     /// Execute the query (using HTTP GET) and retrieve a collection of
     /// label names.
     pub async fn get(self) -> Result<Vec<String>, Error> {
-        let response = self.get_raw().await?;
-        Client::deserialize(response).await
+        let path = "api/v1/labels";
+        let params = self.params();
+
+        if let Some(cached) = self.client.cached(path, &params) {
+            return Ok(cached);
+        }
+
+        let response = self
+            .client
+            .send(path, &params, HttpMethod::GET, None)
+            .await?;
+        let result: Vec<String> = self.client.deserialize(response).await?;
+        self.client.cache(path, &params, &result);
+        Ok(result)
     }
 
     /// Execute the query (using HTTP GET) and retrieve the raw response.
     pub async fn get_raw(self) -> Result<reqwest::Response, Error> {
+        self.client
+            .send("api/v1/labels", &self.params(), HttpMethod::GET, None)
+            .await
+    }
+
+    fn params(&self) -> Vec<(&'static str, String)> {
         let mut params = vec![];
 
         if let Some(start) = self.start {
@@ -537,11 +832,8 @@ impl LabelNamesQueryBuilder {
             params.push(("end", end.to_string()));
         }
 
-        params.extend(self.selectors);
-
-        self.client
-            .send("api/v1/labels", &params, HttpMethod::GET, None)
-            .await
+        params.extend(self.selectors.iter().cloned());
+        params
     }
 }
 
@@ -593,13 +885,32 @@ impl LabelValuesQueryBuilder {
     /// Execute the query (using HTTP GET) and retrieve a collection of
     /// label values for the given label name.
     pub async fn get(self) -> Result<Vec<String>, Error> {
-        let response = self.get_raw().await?;
-        Client::deserialize(response).await
+        let path = format!("api/v1/label/{}/values", self.label);
+        let params = self.params();
+
+        if let Some(cached) = self.client.cached(&path, &params) {
+            return Ok(cached);
+        }
+
+        let response = self
+            .client
+            .send(&path, &params, HttpMethod::GET, None)
+            .await?;
+        let result: Vec<String> = self.client.deserialize(response).await?;
+        self.client.cache(&path, &params, &result);
+        Ok(result)
     }
 
     /// Execute the query (using HTTP GET) and retrieve a collection of
     /// label values for the given label name.
     pub async fn get_raw(self) -> Result<reqwest::Response, Error> {
+        let path = format!("api/v1/label/{}/values", self.label);
+        self.client
+            .send(&path, &self.params(), HttpMethod::GET, None)
+            .await
+    }
+
+    fn params(&self) -> Vec<(&'static str, String)> {
         let mut params = vec![];
 
         if let Some(start) = self.start {
@@ -610,12 +921,42 @@ impl LabelValuesQueryBuilder {
             params.push(("end", end.to_string()));
         }
 
-        params.extend(self.selectors);
+        params.extend(self.selectors.iter().cloned());
+        params
+    }
+}
 
-        let path = format!("api/v1/label/{}/values", self.label);
-        self.client
-            .send(&path, &params, HttpMethod::GET, None)
-            .await
+/// The future returned when awaiting a query builder directly.
+type QueryFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'a>>;
+
+// Awaiting a builder directly is equivalent to calling its `get` method.
+macro_rules! impl_into_future {
+    ($builder:ty => $output:ty) => {
+        impl IntoFuture for $builder {
+            type Output = Result<$output, Error>;
+            type IntoFuture = QueryFuture<'static, $output>;
+
+            fn into_future(self) -> Self::IntoFuture {
+                Box::pin(self.get())
+            }
+        }
+    };
+}
+
+impl_into_future!(InstantQueryBuilder => PromqlResult);
+impl_into_future!(RangeQueryBuilder => PromqlResult);
+impl_into_future!(RulesQueryBuilder => Vec<RuleGroup>);
+impl_into_future!(MetricMetadataQueryBuilder => HashMap<String, Vec<MetricMetadata>>);
+impl_into_future!(SeriesQueryBuilder => Vec<Metric>);
+impl_into_future!(LabelNamesQueryBuilder => Vec<String>);
+impl_into_future!(LabelValuesQueryBuilder => Vec<String>);
+
+impl<'a> IntoFuture for TargetMetadataQueryBuilder<'a> {
+    type Output = Result<Vec<TargetMetadata>, Error>;
+    type IntoFuture = QueryFuture<'a, Vec<TargetMetadata>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.get())
     }
 }
 
@@ -625,6 +966,106 @@ impl LabelValuesQueryBuilder {
 pub struct Client {
     pub(crate) client: reqwest::Client,
     pub(crate) base_url: Url,
+    pub(crate) fail_on_warnings: bool,
+    pub(crate) max_retries: u32,
+    pub(crate) retry_backoff: Duration,
+    pub(crate) max_retry_wait: Duration,
+    pub(crate) query_timeout: Option<Duration>,
+    pub(crate) metadata_timeout: Option<Duration>,
+    pub(crate) token_provider: Option<std::sync::Arc<TokenProvider>>,
+    #[cfg(feature = "cache")]
+    pub(crate) cache: Option<std::sync::Arc<Cache>>,
+}
+
+/// Provides a builder to customize the connection handling of the underlying
+/// [`reqwest::Client`] before creating a [`Client`].
+///
+/// ```rust
+/// use prometheus_http_query::Client;
+/// use std::time::Duration;
+///
+/// let client = Client::builder()
+///     .base_url("https://prometheus.example.com")
+///     .pool_max_idle_per_host(16)
+///     .pool_idle_timeout(Duration::from_secs(30))
+///     .tcp_keepalive(Duration::from_secs(60))
+///     .build();
+///
+/// assert!(client.is_ok());
+/// ```
+#[derive(Debug)]
+pub struct ClientBuilder {
+    builder: reqwest::ClientBuilder,
+    base_url: String,
+}
+
+impl Default for ClientBuilder {
+    /// Create a builder for a [`Client`] that sends requests to "http://127.0.0.1:9090/".
+    fn default() -> Self {
+        ClientBuilder {
+            builder: reqwest::Client::builder(),
+            base_url: String::from("http://127.0.0.1:9090/"),
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// Create a new [`ClientBuilder`], see [`ClientBuilder::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a custom base URL. Note that the API-specific path segments
+    /// (like `/api/v1/query`) are added automatically.
+    /// The URL is checked for correctness when calling [`ClientBuilder::build`].
+    pub fn base_url(mut self, url: impl std::fmt::Display) -> Self {
+        self.base_url = url.to_string();
+        self
+    }
+
+    /// Set headers that are included in every request, e.g. for authentication or
+    /// to route requests through a query frontend. Headers set on a single query
+    /// take precedence over these.
+    /// See also: [`reqwest::ClientBuilder::default_headers`].
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.builder = self.builder.default_headers(headers);
+        self
+    }
+
+    /// Set the maximum number of idle connections that are kept alive per host.
+    /// See also: [`reqwest::ClientBuilder::pool_max_idle_per_host`].
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.builder = self.builder.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// Set a timeout after which idle connections are closed. Pass `None` to keep
+    /// idle connections open indefinitely.
+    /// See also: [`reqwest::ClientBuilder::pool_idle_timeout`].
+    pub fn pool_idle_timeout<D: Into<Option<Duration>>>(mut self, timeout: D) -> Self {
+        self.builder = self.builder.pool_idle_timeout(timeout);
+        self
+    }
+
+    /// Set the interval of TCP keepalive probes. Pass `None` to disable TCP keepalive.
+    /// See also: [`reqwest::ClientBuilder::tcp_keepalive`].
+    pub fn tcp_keepalive<D: Into<Option<Duration>>>(mut self, interval: D) -> Self {
+        self.builder = self.builder.tcp_keepalive(interval);
+        self
+    }
+
+    /// Build the [`Client`]. This fails if the base URL is invalid or the
+    /// underlying [`reqwest::Client`] cannot be initialized.
+    pub fn build(self) -> Result<Client, Error> {
+        let base_url = self.base_url.to_base_url()?;
+        let client = self.builder.build().map_err(|source| {
+            Error::Client(ClientError {
+                message: "failed to build the underlying HTTP client",
+                source: Some(source),
+            })
+        })?;
+        Ok(Client::new(client, base_url))
+    }
 }
 
 impl Default for Client {
@@ -636,10 +1077,10 @@ impl Default for Client {
     /// let client = Client::default();
     /// ```
     fn default() -> Self {
-        Client {
-            client: reqwest::Client::new(),
-            base_url: Url::parse("http://127.0.0.1:9090/").unwrap(),
-        }
+        Client::new(
+            reqwest::Client::new(),
+            Url::parse("http://127.0.0.1:9090/").unwrap(),
+        )
     }
 }
 
@@ -657,11 +1098,8 @@ impl std::str::FromStr for Client {
     /// assert!(client.is_ok());
     /// ```
     fn from_str(url: &str) -> Result<Self, Self::Err> {
-        let client = Client {
-            base_url: url.to_base_url()?,
-            client: reqwest::Client::new(),
-        };
-        Ok(client)
+        let base_url = url.to_base_url()?;
+        Ok(Client::new(reqwest::Client::new(), base_url))
     }
 }
 
@@ -679,11 +1117,8 @@ impl std::convert::TryFrom<&str> for Client {
     /// assert!(client.is_ok());
     /// ```
     fn try_from(url: &str) -> Result<Self, Self::Error> {
-        let client = Client {
-            base_url: url.to_base_url()?,
-            client: reqwest::Client::new(),
-        };
-        Ok(client)
+        let base_url = url.to_base_url()?;
+        Ok(Client::new(reqwest::Client::new(), base_url))
     }
 }
 
@@ -702,15 +1137,65 @@ impl std::convert::TryFrom<String> for Client {
     /// assert!(client.is_ok());
     /// ```
     fn try_from(url: String) -> Result<Self, Self::Error> {
-        let client = Client {
-            base_url: url.to_base_url()?,
-            client: reqwest::Client::new(),
-        };
-        Ok(client)
+        let base_url = url.to_base_url()?;
+        Ok(Client::new(reqwest::Client::new(), base_url))
+    }
+}
+
+impl std::convert::TryFrom<Url> for Client {
+    type Error = crate::error::Error;
+
+    /// Create a [`Client`] from a custom base URL. Note that the API-specific
+    /// path segments (like `/api/v1/query`) are added automatically.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    /// use std::convert::TryFrom;
+    ///
+    /// let url = reqwest::Url::parse("http://proxy.example.com/prometheus").unwrap();
+    /// let client = Client::try_from(url);
+    /// assert!(client.is_ok());
+    /// ```
+    fn try_from(url: Url) -> Result<Self, Self::Error> {
+        let base_url = url.to_base_url()?;
+        Ok(Client::new(reqwest::Client::new(), base_url))
     }
 }
 
 impl Client {
+    fn new(client: reqwest::Client, base_url: Url) -> Self {
+        Client {
+            client,
+            base_url,
+            fail_on_warnings: false,
+            max_retries: 0,
+            retry_backoff: Duration::ZERO,
+            max_retry_wait: DEFAULT_MAX_RETRY_WAIT,
+            query_timeout: None,
+            metadata_timeout: None,
+            token_provider: None,
+            #[cfg(feature = "cache")]
+            cache: None,
+        }
+    }
+
+    /// Create a [`ClientBuilder`] to customize the connection handling of the
+    /// underlying [`reqwest::Client`], e.g. connection pool settings.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// let client = Client::builder()
+    ///     .base_url("http://proxy.example.com/prometheus")
+    ///     .pool_max_idle_per_host(4)
+    ///     .build();
+    ///
+    /// assert!(client.is_ok());
+    /// ```
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
     /// Return a reference to the wrapped [`reqwest::Client`], i.e. to
     /// use it for other requests unrelated to the Prometheus API.
     ///
@@ -778,60 +1263,391 @@ impl Client {
     /// ```
     pub fn from(client: reqwest::Client, url: &str) -> Result<Self, Error> {
         let base_url = url.to_base_url()?;
-        Ok(Client { base_url, client })
+        Ok(Client::new(client, base_url))
     }
 
-    /// Build and send the final HTTP request. Parse the result as JSON if the
-    /// `Content-Type` header indicates that the payload is JSON. Otherwise it is
-    /// assumed that an intermediate proxy sends a plain text error.
-    async fn send<S: Serialize>(
-        &self,
-        path: &str,
-        params: &S,
-        method: HttpMethod,
-        headers: Option<HeaderMap<HeaderValue>>,
-    ) -> Result<reqwest::Response, Error> {
-        let url = build_final_url(self.base_url.clone(), path);
-
-        let mut request = match method {
-            HttpMethod::GET => self.client.get(url).query(params),
-            HttpMethod::POST => self.client.post(url).form(params),
-            _ => unreachable!(),
-        };
-
-        if let Some(headers) = headers {
-            request = request.headers(headers);
-        }
-
-        let response = request.send().await.map_err(|source| {
-            Error::Client(ClientError {
-                message: "failed to send request to server",
-                source: Some(source),
-            })
-        })?;
-        Ok(response)
+    /// Return an [`Error::PartialResponse`] instead of the parsed response data if the
+    /// response contains warnings.<br>
+    /// Prometheus-compatible backends like Thanos or Cortex report that only partial data
+    /// could be retrieved (e.g. because a store is down) using warnings. By default these
+    /// warnings are ignored.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// let client = Client::default().fail_on_warnings(true);
+    /// ```
+    pub fn fail_on_warnings(mut self, enable: bool) -> Self {
+        self.fail_on_warnings = enable;
+        self
     }
 
-    /// Create an [`InstantQueryBuilder`] from a PromQL query allowing you to set some query parameters
-    /// (e.g. evaluation timeout) before finally sending the instant query to the server.
+    /// Retry a request up to `max_retries` times if the server responds with HTTP 429
+    /// (Too Many Requests) or 503 (Service Unavailable), e.g. because a shared query frontend
+    /// throttles requests. Before each retry the client waits as long as the `Retry-After`
+    /// header of the response demands (either in seconds or until an HTTP-date), or for `backoff`
+    /// if the header is missing or invalid.<br>
+    /// The wait demanded by the `Retry-After` header is capped at 60 seconds by default
+    /// (see [`Client::max_retry_wait`]). If the header demands a longer wait, it is ignored
+    /// and the client waits for `backoff` instead. Note that a timeout or deadline applies
+    /// to each attempt separately and does not limit the time spent waiting.<br>
+    /// By default requests are not retried.
     ///
-    /// # Arguments
-    /// * `query` - PromQL query to exeute
-    ///
-    /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#instant-queries)
+    /// Note that waiting requires the time driver of the Tokio runtime to be enabled.
     ///
     /// ```rust
     /// use prometheus_http_query::Client;
+    /// use std::time::Duration;
     ///
-    /// #[tokio::main(flavor = "current_thread")]
-    /// async fn main() -> Result<(), anyhow::Error> {
-    ///     let client = Client::default();
+    /// let client = Client::default().retry(3, Duration::from_secs(1));
+    /// ```
+    pub fn retry(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Set the longest wait the client accepts from a `Retry-After` header before retrying
+    /// a request (see [`Client::retry`]). Longer waits fall back to the fixed backoff.
+    /// Defaults to 60 seconds.
     ///
-    ///     let response = client.query("prometheus_http_request_total").get().await?;
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    /// use std::time::Duration;
     ///
-    ///     assert!(response.data().as_vector().is_some());
+    /// let client = Client::default()
+    ///     .retry(3, Duration::from_secs(1))
+    ///     .max_retry_wait(Duration::from_secs(10));
+    /// ```
+    pub fn max_retry_wait(mut self, max_wait: Duration) -> Self {
+        self.max_retry_wait = max_wait;
+        self
+    }
+
+    /// Abort instant and range queries locally if the server did not respond within the
+    /// given duration. This applies to the HTTP request only, the evaluation timeout of the
+    /// server is left unchanged. A deadline set on a single query (see
+    /// [`InstantQueryBuilder::deadline`] and [`RangeQueryBuilder::deadline`]) takes precedence.
     ///
-    ///     // Or make a POST request.
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    /// use std::time::Duration;
+    ///
+    /// let client = Client::default()
+    ///     .query_timeout(Duration::from_secs(10))
+    ///     .metadata_timeout(Duration::from_secs(60));
+    /// ```
+    pub fn query_timeout(mut self, timeout: Duration) -> Self {
+        self.query_timeout = Some(timeout);
+        self
+    }
+
+    /// Abort metadata requests locally if the server did not respond within the given
+    /// duration, e.g. to allow requests against a large TSDB to take longer than queries.
+    /// Metadata requests are those sent by [`Client::series`], [`Client::label_names`],
+    /// [`Client::label_values`], [`Client::metric_metadata`] and [`Client::target_metadata`].
+    pub fn metadata_timeout(mut self, timeout: Duration) -> Self {
+        self.metadata_timeout = Some(timeout);
+        self
+    }
+
+    /// Return the timeout that applies to requests to the given API endpoint, if any.
+    fn endpoint_timeout(&self, path: &str) -> Option<Duration> {
+        match path {
+            "api/v1/query" | "api/v1/query_range" => self.query_timeout,
+            "api/v1/series" | "api/v1/labels" | "api/v1/metadata" | "api/v1/targets/metadata" => {
+                self.metadata_timeout
+            }
+            p if p.starts_with("api/v1/label/") => self.metadata_timeout,
+            _ => None,
+        }
+    }
+
+    /// Authenticate every request with a bearer token that is obtained from `provider`,
+    /// e.g. to fetch frequently rotating credentials from a sidecar or a cloud IAM service.<br>
+    /// The provider is invoked before each request (including retries). If a `ttl` is given,
+    /// a token is reused for that period before the provider is invoked again. The token
+    /// cache is shared between all clones of this [`Client`].<br>
+    /// An error returned by the provider is returned by the query. Requests that carry
+    /// an explicit `Authorization` header (e.g. set via [`InstantQueryBuilder::header`])
+    /// are sent unchanged.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::{Client, Error};
+    /// use std::time::Duration;
+    ///
+    /// async fn fetch_token() -> Result<String, Error> {
+    ///     // Obtain the token from a sidecar, a file etc.
+    ///     Ok(String::from("secret"))
+    /// }
+    ///
+    /// let client = Client::default().bearer_token_provider(fetch_token, Duration::from_secs(300));
+    /// ```
+    pub fn bearer_token_provider<F, Fut, D>(mut self, provider: F, ttl: D) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, Error>> + Send + 'static,
+        D: Into<Option<Duration>>,
+    {
+        self.token_provider = Some(std::sync::Arc::new(TokenProvider::new(
+            provider,
+            ttl.into(),
+        )));
+        self
+    }
+
+    /// Cache the parsed responses of series, label names and label values queries
+    /// for the given time-to-live. Subsequent identical queries (i.e. with the same
+    /// parameters) within this period are served from the cache without sending a request
+    /// to the server. The cache is shared between all clones of this [`Client`].<br>
+    /// Note that responses obtained via `get_raw` are never cached.
+    ///
+    /// This method is only available if the `cache` feature is enabled.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    /// use std::time::Duration;
+    ///
+    /// let client = Client::default().with_cache(Duration::from_secs(60));
+    /// ```
+    #[cfg(feature = "cache")]
+    pub fn with_cache(mut self, ttl: Duration) -> Self {
+        self.cache = Some(std::sync::Arc::new(Cache::new(ttl)));
+        self
+    }
+
+    /// Look up a cached response for the request to `path` with the given parameters.
+    #[cfg(feature = "cache")]
+    fn cached<T: Clone + 'static>(&self, path: &str, params: &[(&str, String)]) -> Option<T> {
+        self.cache
+            .as_ref()
+            .and_then(|c| c.get(&Cache::key(path, params)))
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn cached<T>(&self, _path: &str, _params: &[(&str, String)]) -> Option<T> {
+        None
+    }
+
+    /// Store the response for the request to `path` with the given parameters.
+    #[cfg(feature = "cache")]
+    fn cache<T>(&self, path: &str, params: &[(&str, String)], value: &T)
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        if let Some(cache) = &self.cache {
+            cache.insert(Cache::key(path, params), value.clone());
+        }
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn cache<T>(&self, _path: &str, _params: &[(&str, String)], _value: &T) {}
+
+    /// Build the final HTTP request without sending it.
+    /// The parameters of POST requests are sent as form fields (repeating the field for
+    /// repeated parameters such as `match[]`), so custom headers are applied first to make
+    /// sure that the `Content-Type` header always reads `application/x-www-form-urlencoded`.
+    fn request<S: Serialize>(
+        &self,
+        path: &str,
+        params: &S,
+        method: HttpMethod,
+        headers: Option<HeaderMap<HeaderValue>>,
+    ) -> reqwest::RequestBuilder {
+        let url = build_final_url(self.base_url.clone(), path);
+
+        let mut request = match method {
+            HttpMethod::GET => self.client.get(url),
+            HttpMethod::POST => self.client.post(url),
+            _ => unreachable!(),
+        };
+
+        if let Some(headers) = headers {
+            request = request.headers(headers);
+        }
+
+        if let Some(timeout) = self.endpoint_timeout(path) {
+            request = request.timeout(timeout);
+        }
+
+        match method {
+            HttpMethod::POST => request.form(params),
+            _ => request.query(params),
+        }
+    }
+
+    /// Build and send the final HTTP request. Parse the result as JSON if the
+    /// `Content-Type` header indicates that the payload is JSON. Otherwise it is
+    /// assumed that an intermediate proxy sends a plain text error.
+    async fn send<S: Serialize>(
+        &self,
+        path: &str,
+        params: &S,
+        method: HttpMethod,
+        headers: Option<HeaderMap<HeaderValue>>,
+    ) -> Result<reqwest::Response, Error> {
+        self.execute(path, self.request(path, params, method, headers))
+            .await
+    }
+
+    /// Send a previously built HTTP request to the given API endpoint.
+    async fn execute(
+        &self,
+        path: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        let mut request = request.build().map_err(|source| {
+            Error::Client(ClientError {
+                message: "failed to build request",
+                source: Some(source),
+            })
+        })?;
+
+        let authorize =
+            self.token_provider.is_some() && !request.headers().contains_key(AUTHORIZATION);
+
+        let mut retries = 0;
+        loop {
+            if authorize {
+                self.authorize(&mut request).await?;
+            }
+            let next = match retries < self.max_retries {
+                true => request.try_clone(),
+                false => None,
+            };
+            let response = self.dispatch(path, request).await?;
+            let status = response.status();
+            match next {
+                Some(next)
+                    if status == StatusCode::TOO_MANY_REQUESTS
+                        || status == StatusCode::SERVICE_UNAVAILABLE =>
+                {
+                    let wait = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|v| util::parse_retry_after(v, time::OffsetDateTime::now_utc()))
+                        .filter(|wait| *wait <= self.max_retry_wait)
+                        .unwrap_or(self.retry_backoff);
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        status = status.as_u16(),
+                        wait_ms = wait.as_millis() as u64,
+                        "retrying request to Prometheus"
+                    );
+                    tokio::time::sleep(wait).await;
+                    request = next;
+                    retries += 1;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+
+    /// Set the `Authorization` header of the request to a token from the token provider.
+    async fn authorize(&self, request: &mut reqwest::Request) -> Result<(), Error> {
+        if let Some(provider) = &self.token_provider {
+            let token = provider.token().await?;
+            let mut value = HeaderValue::try_from(format!("Bearer {}", token)).map_err(|_| {
+                Error::Client(ClientError {
+                    message: "failed to build authorization header from bearer token",
+                    source: None,
+                })
+            })?;
+            value.set_sensitive(true);
+            request.headers_mut().insert(AUTHORIZATION, value);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    async fn dispatch(
+        &self,
+        _path: &str,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, Error> {
+        self.send_request(request).await
+    }
+
+    /// Send the request within a span that records the endpoint, HTTP method and
+    /// the outcome of the request.
+    #[cfg(feature = "tracing")]
+    async fn dispatch(
+        &self,
+        path: &str,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, Error> {
+        use tracing::{field, Instrument};
+
+        let span = tracing::info_span!(
+            "prometheus_request",
+            endpoint = path,
+            method = %request.method(),
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+            error = field::Empty,
+        );
+        let start = std::time::Instant::now();
+        let result = self.send_request(request).instrument(span.clone()).await;
+        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+        match &result {
+            Ok(response) => span.record("status", response.status().as_u16()),
+            Err(e) => match std::error::Error::source(e) {
+                Some(source) => span.record("error", field::display(format!("{}: {}", e, source))),
+                None => span.record("error", field::display(e)),
+            },
+        };
+        result
+    }
+
+    async fn send_request(&self, request: reqwest::Request) -> Result<reqwest::Response, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            method = %request.method(),
+            url = %request.url(),
+            "sending request to Prometheus"
+        );
+
+        self.client.execute(request).await.map_err(|source| {
+            Error::Client(ClientError {
+                message: "failed to send request to server",
+                source: Some(source),
+            })
+        })
+    }
+
+    /// Build the final URL of a GET request including its query string.
+    fn build_url<S: Serialize>(&self, path: &str, params: &S) -> Result<Url, Error> {
+        self.request(path, params, HttpMethod::GET, None)
+            .build()
+            .map(|r| r.url().clone())
+            .map_err(|source| {
+                Error::Client(ClientError {
+                    message: "failed to build request URL",
+                    source: Some(source),
+                })
+            })
+    }
+
+    /// Create an [`InstantQueryBuilder`] from a PromQL query allowing you to set some query parameters
+    /// (e.g. evaluation timeout) before finally sending the instant query to the server.
+    ///
+    /// # Arguments
+    /// * `query` - PromQL query to exeute
+    ///
+    /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#instant-queries)
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), anyhow::Error> {
+    ///     let client = Client::default();
+    ///
+    ///     let response = client.query("prometheus_http_request_total").get().await?;
+    ///
+    ///     assert!(response.data().as_vector().is_some());
+    ///
+    ///     // Or make a POST request.
     ///     let response = client.query("prometheus_http_request_total").post().await?;
     ///
     ///     assert!(response.data().as_vector().is_some());
@@ -842,8 +1658,10 @@ impl Client {
     pub fn query(&self, query: impl std::fmt::Display) -> InstantQueryBuilder {
         InstantQueryBuilder {
             client: self.clone(),
-            params: vec![("query", query.to_string())],
+            params: vec![(Cow::Borrowed("query"), query.to_string())],
             headers: Default::default(),
+            http_timeout: None,
+            time: None,
         }
     }
 
@@ -856,6 +1674,9 @@ impl Client {
     /// * `end` - End timestamp as Unix timestamp (seconds)
     /// * `step` - Query resolution step width as float number of seconds
     ///
+    /// Executing the query fails with [`Error::InvalidTimeRange`] if `start` is greater than `end`
+    /// and with [`Error::InvalidStep`] if `step` is not a positive number.
+    ///
     /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#range-queries)
     ///
     /// ```rust
@@ -889,15 +1710,220 @@ impl Client {
         RangeQueryBuilder {
             client: self.clone(),
             params: vec![
-                ("query", query.to_string()),
-                ("start", start.to_string()),
-                ("end", end.to_string()),
-                ("step", step.to_string()),
+                (Cow::Borrowed("query"), query.to_string()),
+                (Cow::Borrowed("start"), start.to_string()),
+                (Cow::Borrowed("end"), end.to_string()),
+                (Cow::Borrowed("step"), step.to_string()),
             ],
             headers: Default::default(),
+            http_timeout: None,
+            start,
+            end,
+            step,
+            max_points: None,
         }
     }
 
+    /// Execute a range query (using HTTP GET) over a long time range by splitting it into
+    /// consecutive sub-ranges of `chunk` duration that are queried concurrently, e.g. to stay
+    /// below the maximum number of points per time series that the server permits.
+    /// At most four sub-ranges are queried at the same time.<br>
+    /// The matrices of all sub-ranges are stitched together into a single matrix that contains
+    /// each time series once. Samples at timestamps shared by two sub-ranges appear only once.
+    /// The chunk duration is rounded up to a multiple of `step`, so that all samples lie on the
+    /// same grid as they would in a single query (this is exact for whole-second steps).
+    /// Query statistics are not retained. If the server returns anything but a matrix for
+    /// a sub-range, [`Error::Client`] is returned.
+    ///
+    /// # Arguments
+    /// * `query` - PromQL query to exeute
+    /// * `start` - Start timestamp as Unix timestamp (seconds)
+    /// * `end` - End timestamp as Unix timestamp (seconds)
+    /// * `step` - Query resolution step width as float number of seconds
+    /// * `chunk` - Duration of each sub-range
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), anyhow::Error> {
+    ///     let client = Client::default();
+    ///
+    ///     // One week at a resolution of 15 seconds, queried in chunks of one day.
+    ///     let day = Duration::from_secs(86400);
+    ///     let response = client
+    ///         .query_range_chunked("up", 1648373100, 1648977900, 15.0, day)
+    ///         .await?;
+    ///
+    ///     assert!(response.data().as_matrix().is_some());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_range_chunked(
+        &self,
+        query: impl std::fmt::Display,
+        start: i64,
+        end: i64,
+        step: f64,
+        chunk: Duration,
+    ) -> Result<PromqlResult, Error> {
+        use futures_util::stream::{self, StreamExt};
+
+        // Validate the overall range and step first to not send any request in vain.
+        validate_range(start, end, step)?;
+
+        let query = query.to_string();
+        let requests = util::split_range(start, end, step, chunk)
+            .into_iter()
+            .map(|(start, end)| self.query_range(&query, start, end, step).get());
+        let mut results = stream::iter(requests).buffered(MAX_CONCURRENT_CHUNKS);
+
+        let mut chunks = vec![];
+        while let Some(result) = results.next().await {
+            match result?.into_data() {
+                Data::Matrix(matrix) => chunks.push(matrix),
+                _ => {
+                    return Err(Error::Client(ClientError {
+                        message: "expected a matrix result from a range query",
+                        source: None,
+                    }))
+                }
+            }
+        }
+
+        Ok(PromqlResult {
+            data: Data::Matrix(stitch_matrices(chunks)),
+            stats: None,
+            evaluation_time: None,
+        })
+    }
+
+    /// Execute an instant query (using HTTP GET) and return the complete JSON response as
+    /// a generic [`serde_json::Value`] instead of the typed [`PromqlResult`], e.g. to read fields
+    /// that this crate does not support yet.<br>
+    /// Like [`InstantQueryBuilder::get`] this fails if Prometheus reports an error.
+    ///
+    /// # Arguments
+    /// * `query` - PromQL query to exeute
+    /// * `time` - Evaluation timestamp as Unix timestamp (seconds), defaults to the current server time
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), anyhow::Error> {
+    ///     let client = Client::default();
+    ///
+    ///     let response = client.query_raw("up", None).await?;
+    ///
+    ///     assert!(response["data"]["result"].is_array());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_raw(
+        &self,
+        query: impl std::fmt::Display,
+        time: Option<i64>,
+    ) -> Result<serde_json::Value, Error> {
+        let builder = match time {
+            Some(time) => self.query(query).at(time),
+            None => self.query(query),
+        };
+        let response = builder.get_raw().await?;
+        self.deserialize_value(response).await
+    }
+
+    /// Execute a range query (using HTTP GET) and return the complete JSON response as
+    /// a generic [`serde_json::Value`], see [`Client::query_raw`] and [`Client::query_range`].
+    pub async fn query_range_raw(
+        &self,
+        query: impl std::fmt::Display,
+        start: i64,
+        end: i64,
+        step: f64,
+    ) -> Result<serde_json::Value, Error> {
+        let response = self.query_range(query, start, end, step).get_raw().await?;
+        self.deserialize_value(response).await
+    }
+
+    /// Execute a range query (using HTTP GET) with a step width that is chosen such that
+    /// the result contains at most `max_points` samples per time series. This is useful
+    /// e.g. to fit the resolution of a query to the width of a graph.<br>
+    /// The step width is a whole number of seconds and never below one second. It is returned
+    /// alongside the parsed API response.
+    ///
+    /// # Arguments
+    /// * `query` - PromQL query to exeute
+    /// * `start` - Start timestamp as Unix timestamp (seconds)
+    /// * `end` - End timestamp as Unix timestamp (seconds)
+    /// * `max_points` - Maximum number of samples per time series (values below 1 are treated as 1)
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), anyhow::Error> {
+    ///     let client = Client::default();
+    ///
+    ///     let q = "prometheus_http_requests_total";
+    ///
+    ///     let (response, step) = client.query_range_fit(q, 1648373100, 1648376700, 61).await?;
+    ///
+    ///     assert!(response.data().as_matrix().is_some());
+    ///     assert_eq!(step, Duration::from_secs(60));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_range_fit(
+        &self,
+        query: impl std::fmt::Display,
+        start: i64,
+        end: i64,
+        max_points: u64,
+    ) -> Result<(PromqlResult, Duration), Error> {
+        let step = util::fit_step(start, end, max_points);
+        let result = self
+            .query_range(query, start, end, step as f64)
+            .get()
+            .await?;
+        Ok((result, Duration::from_secs(step)))
+    }
+
+    /// Let Prometheus format the given PromQL expression and return it in its
+    /// normalized, pretty-printed form, e.g. for auto-formatting in a query editor.
+    ///
+    /// An invalid expression results in an [`Error::Prometheus`] of type
+    /// [`PrometheusErrorType::BadData`](crate::error::PrometheusErrorType::BadData).
+    ///
+    /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#formatting-query-expressions)
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), anyhow::Error> {
+    ///     let client = Client::default();
+    ///
+    ///     let formatted = client.format_query("foo/bar").await?;
+    ///
+    ///     assert_eq!(formatted, "foo / bar");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn format_query(&self, query: impl std::fmt::Display) -> Result<String, Error> {
+        let params = [("query", query.to_string())];
+        let response = self
+            .send("api/v1/format_query", &params, HttpMethod::GET, None)
+            .await?;
+        self.deserialize(response).await
+    }
+
     /// Create a [`SeriesQueryBuilder`] to apply filters to a series metadata
     /// query before sending it to Prometheus.
     ///
@@ -1031,6 +2057,35 @@ impl Client {
         }
     }
 
+    /// Create a [`LabelValuesQueryBuilder`] to retrieve the names of all metrics, i.e. all
+    /// values of the `__name__` label. This is a shorthand for `label_values("__name__")`,
+    /// so the same filters may be applied.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::{Client, Selector};
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), anyhow::Error> {
+    ///     let client = Client::default();
+    ///
+    ///     let names = client.metric_names().get().await?;
+    ///
+    ///     assert!(names.iter().any(|n| n == "up"));
+    ///
+    ///     // Only list the metrics exposed by a specific job:
+    ///     let s1 = Selector::new().eq("job", "prometheus");
+    ///
+    ///     let response = client.metric_names().selectors(&[s1]).get().await;
+    ///
+    ///     assert!(response.is_ok());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn metric_names(&self) -> LabelValuesQueryBuilder {
+        self.label_values("__name__")
+    }
+
     /// Query the current state of target discovery.
     ///
     /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#targets)
@@ -1064,7 +2119,7 @@ impl Client {
         let response = self
             .send("api/v1/targets", &params, HttpMethod::GET, None)
             .await?;
-        Client::deserialize(response).await
+        self.deserialize(response).await
     }
 
     /// Create a [`RulesQueryBuilder`] to apply filters to the rules query before
@@ -1123,9 +2178,7 @@ impl Client {
         let response = self
             .send("api/v1/alerts", &(), HttpMethod::GET, None)
             .await?;
-        Client::deserialize(response)
-            .await
-            .map(|r: Alerts| r.alerts)
+        self.deserialize(response).await.map(|r: Alerts| r.alerts)
     }
 
     /// Retrieve a list of flags that Prometheus was configured with.
@@ -1150,7 +2203,7 @@ impl Client {
         let response = self
             .send("api/v1/status/flags", &(), HttpMethod::GET, None)
             .await?;
-        Client::deserialize(response).await
+        self.deserialize(response).await
     }
 
     /// Retrieve Prometheus server build information.
@@ -1175,7 +2228,7 @@ impl Client {
         let response = self
             .send("api/v1/status/buildinfo", &(), HttpMethod::GET, None)
             .await?;
-        Client::deserialize(response).await
+        self.deserialize(response).await
     }
 
     /// Retrieve Prometheus server runtime information.
@@ -1200,7 +2253,7 @@ impl Client {
         let response = self
             .send("api/v1/status/runtimeinfo", &(), HttpMethod::GET, None)
             .await?;
-        Client::deserialize(response).await
+        self.deserialize(response).await
     }
 
     /// Retrieve Prometheus TSDB statistics.
@@ -1225,7 +2278,38 @@ impl Client {
         let response = self
             .send("api/v1/status/tsdb", &(), HttpMethod::GET, None)
             .await?;
-        Client::deserialize(response).await
+        self.deserialize(response).await
+    }
+
+    /// Retrieve Prometheus TSDB statistics, limiting the number of items returned for each
+    /// list of the statistics (Prometheus returns the top 10 by default).
+    ///
+    /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#tsdb-stats)
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), anyhow::Error> {
+    ///     let client = Client::default();
+    ///
+    ///     let response = client.tsdb_statistics_with_limit(3).await?;
+    ///
+    ///     assert!(response.series_count_by_metric_name().len() <= 3);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn tsdb_statistics_with_limit(&self, limit: usize) -> Result<TsdbStatistics, Error> {
+        let response = self
+            .send(
+                "api/v1/status/tsdb",
+                &[("limit", limit.to_string())],
+                HttpMethod::GET,
+                None,
+            )
+            .await?;
+        self.deserialize(response).await
     }
 
     /// Retrieve WAL replay statistics.
@@ -1250,7 +2334,34 @@ impl Client {
         let response = self
             .send("api/v1/status/walreplay", &(), HttpMethod::GET, None)
             .await?;
-        Client::deserialize(response).await
+        self.deserialize(response).await
+    }
+
+    /// Retrieve the notifications that Prometheus currently reports about its own
+    /// operational state, e.g. a failed configuration reload.
+    /// This endpoint is available as of Prometheus 3.0.
+    ///
+    /// See also: [Prometheus API documentation](https://prometheus.io/docs/prometheus/latest/querying/api/#notifications)
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Client;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), anyhow::Error> {
+    ///     let client = Client::default();
+    ///
+    ///     let response = client.notifications().await;
+    ///
+    ///     assert!(response.is_ok());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn notifications(&self) -> Result<Vec<Notification>, Error> {
+        let response = self
+            .send("api/v1/notifications", &(), HttpMethod::GET, None)
+            .await?;
+        self.deserialize(response).await
     }
 
     /// Query the current state of alertmanager discovery.
@@ -1275,7 +2386,7 @@ impl Client {
         let response = self
             .send("api/v1/alertmanagers", &(), HttpMethod::GET, None)
             .await?;
-        Client::deserialize(response).await
+        self.deserialize(response).await
     }
 
     /// Create a [`TargetMetadataQueryBuilder`] to apply filters to a target metadata
@@ -1445,7 +2556,31 @@ impl Client {
     //
     // Internally, the response is deserialized into the [`ApiResponse`] type first.
     // On success, the data is returned as is. On failure, the error is mapped to the appropriate [`Error`] type.
-    async fn deserialize<D: DeserializeOwned>(response: reqwest::Response) -> Result<D, Error> {
+    async fn deserialize<D: DeserializeOwned>(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<D, Error> {
+        let response = Self::json::<ApiResponse<D>>(response).await?;
+        self.check(response)
+    }
+
+    // Deserialize the raw reqwest response into a generic JSON value while still checking
+    // the status of the response like [`Client::deserialize`] does.
+    async fn deserialize_value(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<serde_json::Value, Error> {
+        let value = Self::json::<serde_json::Value>(response).await?;
+        let response = ApiResponse::<IgnoredAny>::deserialize(&value).map_err(|_| {
+            Error::Client(ClientError {
+                message: "failed to parse JSON response from server",
+                source: None,
+            })
+        })?;
+        self.check(response).map(|_| value)
+    }
+
+    async fn json<D: DeserializeOwned>(response: reqwest::Response) -> Result<D, Error> {
         let header = CONTENT_TYPE;
         if !util::is_json(response.headers().get(header)) {
             return Err(Error::Client(ClientError {
@@ -1453,15 +2588,1306 @@ impl Client {
                 source: response.error_for_status().err(),
             }));
         }
-        let response = response.json::<ApiResponse<D>>().await.map_err(|source| {
+        response.json::<D>().await.map_err(|source| {
             Error::Client(ClientError {
                 message: "failed to parse JSON response from server",
                 source: Some(source),
             })
-        })?;
+        })
+    }
+
+    fn check<D>(&self, response: ApiResponse<D>) -> Result<D, Error> {
         match response {
-            ApiResponse::Success { data } => Ok(data),
+            ApiResponse::Success { warnings, .. }
+                if self.fail_on_warnings && !warnings.is_empty() =>
+            {
+                Err(Error::PartialResponse(warnings))
+            }
+            ApiResponse::Success {
+                data: Some(data), ..
+            } => Ok(data),
+            ApiResponse::Success { data: None, .. } => Err(Error::MissingData),
             ApiResponse::Error(e) => Err(Error::Prometheus(e)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_client_builder_with_pool_settings() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::UrlEncoded("query".into(), "up".into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#)
+            .create_async()
+            .await;
+
+        let client = Client::builder()
+            .base_url(server.url())
+            .pool_max_idle_per_host(2)
+            .pool_idle_timeout(Duration::from_secs(10))
+            .tcp_keepalive(Duration::from_secs(30))
+            .build()?;
+
+        let response = client.query("up").get().await?;
+        assert!(response.data().as_vector().is_some_and(|v| v.is_empty()));
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_builder_invalid_url() {
+        let client = Client::builder().base_url("not a url").build();
+        assert!(matches!(client, Err(Error::ParseUrl(_))));
+    }
+
+    #[test]
+    fn test_instant_query_build_url() -> Result<(), anyhow::Error> {
+        let client = Client::try_from("http://proxy.example.com/prometheus")?;
+        let url = client
+            .query(r#"sum(rate(http_requests_total{code="200"}[5m]))"#)
+            .at(1659182624)
+            .build_url()?;
+        assert_eq!(url.path(), "/prometheus/api/v1/query");
+        let query = url.query().unwrap();
+        assert!(query.contains(
+            "query=sum%28rate%28http_requests_total%7Bcode%3D%22200%22%7D%5B5m%5D%29%29"
+        ));
+        assert!(query.contains("time=1659182624"));
+        let pairs = url.query_pairs().into_owned().collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            vec![
+                (
+                    "query".to_string(),
+                    r#"sum(rate(http_requests_total{code="200"}[5m]))"#.to_string()
+                ),
+                ("time".to_string(), "1659182624".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_query_build_url() -> Result<(), anyhow::Error> {
+        let client = Client::default();
+        let url = client
+            .query_range("up", 1648373100, 1648373300, 10.0)
+            .build_url()?;
+        assert_eq!(
+            url.as_str(),
+            "http://127.0.0.1:9090/api/v1/query_range?query=up&start=1648373100&end=1648373300&step=10"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_instant_query_deadline() -> Result<(), anyhow::Error> {
+        let client = Client::default();
+        let request = client
+            .query("up")
+            .deadline(Duration::from_millis(1500))
+            .request(HttpMethod::POST)
+            .build()?;
+        assert_eq!(request.timeout(), Some(&Duration::from_millis(2500)));
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap();
+        assert_eq!(body, b"query=up&timeout=1500ms");
+        Ok(())
+    }
+
+    #[test]
+    fn test_extra_query_params() -> Result<(), anyhow::Error> {
+        let client = Client::default();
+        let query = client.query("up").query("foo", "bar").query("foo", 42);
+        let url = query.build_url()?;
+        assert_eq!(
+            url.as_str(),
+            "http://127.0.0.1:9090/api/v1/query?query=up&foo=bar&foo=42"
+        );
+        let request = query.request(HttpMethod::POST).build()?;
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap();
+        assert_eq!(body, b"query=up&foo=bar&foo=42");
+
+        let url = client
+            .query_range("up", 1648373100, 1648373300, 10.0)
+            .query("foo", "bar")
+            .build_url()?;
+        assert!(url.as_str().ends_with("&step=10&foo=bar"));
+
+        // Parameter names may also be built at runtime.
+        let names = [String::from("dedup"), format!("{}[]", "replicaLabels")];
+        let query = names.iter().fold(client.query("up"), |q, name| {
+            q.extra_param(name.as_str(), 1)
+        });
+        let url = query.build_url()?;
+        assert_eq!(
+            url.as_str(),
+            "http://127.0.0.1:9090/api/v1/query?query=up&dedup=1&replicaLabels%5B%5D=1"
+        );
+        let url = client
+            .query_range("up", 1648373100, 1648373300, 10.0)
+            .extra_param(names[0].clone(), false)
+            .build_url()?;
+        assert!(url.as_str().ends_with("&step=10&dedup=false"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_endpoint_timeouts() -> Result<(), anyhow::Error> {
+        let client = Client::default()
+            .query_timeout(Duration::from_secs(10))
+            .metadata_timeout(Duration::from_secs(60));
+        let timeout = |path: &str| -> Result<Option<Duration>, anyhow::Error> {
+            let params: [(&str, String); 0] = [];
+            let request = client
+                .request(path, &params, HttpMethod::GET, None)
+                .build()?;
+            Ok(request.timeout().copied())
+        };
+
+        let request = client.query("up").request(HttpMethod::GET).build()?;
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(10)));
+        let request = client
+            .query_range("up", 1648373100, 1648373300, 10.0)
+            .request(HttpMethod::POST)
+            .build()?;
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(10)));
+        assert_eq!(timeout("api/v1/series")?, Some(Duration::from_secs(60)));
+        assert_eq!(timeout("api/v1/labels")?, Some(Duration::from_secs(60)));
+        assert_eq!(
+            timeout("api/v1/label/job/values")?,
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(timeout("api/v1/metadata")?, Some(Duration::from_secs(60)));
+        assert_eq!(timeout("api/v1/rules")?, None);
+
+        // The deadline of a single query takes precedence.
+        let request = client
+            .query("up")
+            .deadline(Duration::from_secs(2))
+            .request(HttpMethod::GET)
+            .build()?;
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(3)));
+
+        let request = Client::default()
+            .query("up")
+            .request(HttpMethod::GET)
+            .build()?;
+        assert_eq!(request.timeout(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_post_request_form_encoding() -> Result<(), anyhow::Error> {
+        let client = Client::default();
+        let params = [
+            ("match[]", String::from("up")),
+            (
+                "match[]",
+                String::from(r#"process_start_time_seconds{job="prometheus"}"#),
+            ),
+            ("start", String::from("1648373100")),
+        ];
+        let request = client
+            .request("api/v1/series", &params, HttpMethod::POST, None)
+            .build()?;
+        assert_eq!(
+            request.headers().get(CONTENT_TYPE),
+            Some(&HeaderValue::from_static(
+                "application/x-www-form-urlencoded"
+            ))
+        );
+        assert!(request.url().query().is_none());
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap();
+        assert_eq!(
+            std::str::from_utf8(body)?,
+            "match%5B%5D=up&match%5B%5D=process_start_time_seconds%7Bjob%3D%22prometheus%22%7D&start=1648373100"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_request_content_type() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/query")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body("query=up")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#)
+            .create_async()
+            .await;
+
+        // A custom Content-Type header does not override the form encoding.
+        let client = Client::try_from(server.url())?;
+        client
+            .query("up")
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .post()
+            .await?;
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[test]
+    fn test_timeout_and_deadline_replace_each_other() -> Result<(), anyhow::Error> {
+        let client = Client::default();
+        let request = client
+            .query("up")
+            .timeout(5000)
+            .deadline(Duration::from_secs(1))
+            .request(HttpMethod::POST)
+            .build()?;
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(2)));
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap();
+        assert_eq!(body, b"query=up&timeout=1000ms");
+
+        let request = client
+            .query("up")
+            .deadline(Duration::from_secs(1))
+            .timeout(5000)
+            .request(HttpMethod::POST)
+            .build()?;
+        assert_eq!(request.timeout(), None);
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap();
+        assert_eq!(body, b"query=up&timeout=5000ms");
+
+        let url = client
+            .query_range("up", 1648373100, 1648373300, 10.0)
+            .timeout(5000)
+            .deadline(Duration::from_secs(1))
+            .deadline(Duration::from_secs(3))
+            .build_url()?;
+        assert_eq!(
+            url.as_str(),
+            "http://127.0.0.1:9090/api/v1/query_range?query=up&start=1648373100&end=1648373300&step=10&timeout=3000ms"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_query_deadline() -> Result<(), anyhow::Error> {
+        let client = Client::default();
+        let request = client
+            .query_range("up", 1648373100, 1648373300, 10.0)
+            .deadline(Duration::from_secs(5))
+            .request(HttpMethod::GET)
+            .build()?;
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(6)));
+        assert!(request.url().query().unwrap().ends_with("&timeout=5000ms"));
+
+        let request = client
+            .query_range("up", 1648373100, 1648373300, 10.0)
+            .request(HttpMethod::GET)
+            .build()?;
+        assert_eq!(request.timeout(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_try_from() -> Result<(), anyhow::Error> {
+        let client = Client::try_from("http://proxy.example.com/prometheus")?;
+        assert_eq!(
+            client.base_url().as_str(),
+            "http://proxy.example.com/prometheus"
+        );
+
+        let client = Client::try_from(String::from("https://prometheus.example.com"))?;
+        assert_eq!(
+            client.base_url().as_str(),
+            "https://prometheus.example.com/"
+        );
+
+        let url = Url::parse("http://localhost:9090/prometheus/")?;
+        let client = Client::try_from(url.clone())?;
+        assert_eq!(client.base_url(), &url);
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_try_from_without_scheme() {
+        for url in ["localhost:9090", "prometheus.example.com"] {
+            match Client::try_from(url) {
+                Err(Error::ParseUrl(e)) => {
+                    assert_eq!(e.inner(), &url::ParseError::RelativeUrlWithoutBase)
+                }
+                _ => panic!("expected a URL parsing error for {}", url),
+            }
+            assert!(Client::try_from(url.to_string()).is_err());
+        }
+
+        let url = Url::parse("localhost:9090").unwrap();
+        assert!(matches!(Client::try_from(url), Err(Error::ParseUrl(_))));
+    }
+
+    #[tokio::test]
+    async fn test_series_selectors_are_sent_as_separate_params() -> Result<(), anyhow::Error> {
+        let selectors = [
+            Selector::new().metric("up"),
+            Selector::new().eq("job", "node").regex_eq("mode", ".+"),
+            Selector::new()
+                .metric("process_start_time_seconds")
+                .eq("job", "prometheus"),
+        ];
+        // The selectors must be sent as repeated parameters, not as a single joined one.
+        let query = selectors
+            .iter()
+            .fold(
+                url::form_urlencoded::Serializer::new(String::new()),
+                |mut q, s| {
+                    q.append_pair("match[]", &s.to_string());
+                    q
+                },
+            )
+            .finish();
+
+        let mut server = mockito::Server::new_async().await;
+        let series = server
+            .mock("GET", "/api/v1/series")
+            .match_query(mockito::Matcher::Exact(query.clone()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":[]}"#)
+            .create_async()
+            .await;
+        let labels = server
+            .mock("GET", "/api/v1/labels")
+            .match_query(mockito::Matcher::Exact(query))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":[]}"#)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        client.series(&selectors)?.get().await?;
+        client.label_names().selectors(&selectors).get().await?;
+        series.assert_async().await;
+        labels.assert_async().await;
+
+        let request = client
+            .request(
+                "api/v1/series",
+                &client.series(&selectors)?.selectors,
+                HttpMethod::GET,
+                None,
+            )
+            .build()?;
+        let params = request.url().query_pairs().into_owned().collect::<Vec<_>>();
+        assert_eq!(params.len(), 3);
+        for ((key, value), selector) in params.iter().zip(&selectors) {
+            assert_eq!(key, "match[]");
+            assert_eq!(value, &selector.to_string());
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn test_cached_metadata_queries() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let series = server
+            .mock("GET", "/api/v1/series")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":[{"__name__":"up","job":"node"}]}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let labels = server
+            .mock("GET", "/api/v1/labels")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":["__name__","job"]}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let values = server
+            .mock("GET", "/api/v1/label/job/values")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":["node"]}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?.with_cache(Duration::from_secs(60));
+        let select = Selector::new().metric("up");
+        let first = client.series([&select])?.get().await?;
+        let second = client.series([&select])?.get().await?;
+        assert_eq!(first, second);
+        // Different parameters are cached separately.
+        client.series([&select])?.start(1648373100).get().await?;
+        series.assert_async().await;
+
+        for _ in 0..3 {
+            let names = client.label_names().get().await?;
+            assert_eq!(names, vec!["__name__", "job"]);
+            let values = client.clone().label_values("job").get().await?;
+            assert_eq!(values, vec!["node"]);
+        }
+        labels.assert_async().await;
+        values.assert_async().await;
+        Ok(())
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn test_expired_cache_entries() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let labels = server
+            .mock("GET", "/api/v1/labels")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":["__name__"]}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?.with_cache(Duration::ZERO);
+        client.label_names().get().await?;
+        client.label_names().get().await?;
+        labels.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_instant_query_evaluation_time() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/query")
+            .match_body(mockito::Matcher::UrlEncoded("time".into(), "1659182624".into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[{"metric":{"__name__":"up"},"value":[1659182610.5,"1"]}]}}"#)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        let result = client.query("up").at(1659182624).post().await?;
+        mock.assert_async().await;
+        assert_eq!(result.evaluation_time(), Some(1659182624));
+        let vector = result.data().as_vector().unwrap();
+        assert_eq!(vector[0].timestamp(), 1659182610.5);
+
+        let result =
+            serde_json::from_str::<PromqlResult>(r#"{"resultType":"vector","result":[]}"#)?;
+        assert_eq!(result.evaluation_time(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fail_on_warnings() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","warnings":["partial response due to down store"],"data":{"resultType":"vector","result":[{"metric":{"__name__":"up"},"value":[1659182624,"1"]}]}}"#)
+            .expect(3)
+            .create_async()
+            .await;
+
+        // Warnings are ignored by default.
+        let client = Client::try_from(server.url())?;
+        let result = client.query("up").get().await?;
+        assert_eq!(result.data().as_vector().map(|v| v.len()), Some(1));
+
+        let result = client
+            .clone()
+            .fail_on_warnings(false)
+            .query("up")
+            .get()
+            .await?;
+        assert!(result.data().as_vector().is_some());
+
+        let client = client.fail_on_warnings(true);
+        match client.query("up").get().await {
+            Err(Error::PartialResponse(warnings)) => {
+                assert_eq!(warnings, vec!["partial response due to down store"])
+            }
+            _ => panic!("expected a partial response error"),
+        }
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fail_on_warnings_without_warnings() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/labels")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","warnings":[],"data":["job"]}"#)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?.fail_on_warnings(true);
+        assert_eq!(client.label_names().get().await?, vec!["job"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_format_query() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let valid = server
+            .mock("GET", "/api/v1/format_query")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "query".into(),
+                "foo/bar".into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":"foo / bar"}"#)
+            .create_async()
+            .await;
+        let invalid = server
+            .mock("GET", "/api/v1/format_query")
+            .match_query(mockito::Matcher::UrlEncoded("query".into(), "foo/".into()))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"error","errorType":"bad_data","error":"1:5: parse error: unexpected end of input"}"#)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        assert_eq!(client.format_query("foo/bar").await?, "foo / bar");
+        match client.format_query("foo/").await {
+            Err(Error::Prometheus(e)) => {
+                assert!(e.is_bad_data());
+                assert_eq!(e.message(), "1:5: parse error: unexpected end of input");
+            }
+            _ => panic!("expected a bad_data error"),
+        }
+        valid.assert_async().await;
+        invalid.assert_async().await;
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_accepts_string_like_types() -> Result<(), anyhow::Error> {
+        use std::borrow::Cow;
+
+        let client = Client::default();
+        let owned = String::from("up");
+        let expected = client.query("up").build_url()?;
+        assert_eq!(client.query(owned.clone()).build_url()?, expected);
+        assert_eq!(client.query(&owned).build_url()?, expected);
+        assert_eq!(client.query(owned.as_str()).build_url()?, expected);
+        assert_eq!(client.query(Cow::Borrowed("up")).build_url()?, expected);
+        assert_eq!(
+            client.query(Cow::<str>::Owned(owned.clone())).build_url()?,
+            expected
+        );
+        let select = Selector::new().metric(owned.clone());
+        assert_eq!(
+            client.query(&select).build_url()?,
+            client.query(r#"{__name__="up"}"#).build_url()?
+        );
+        assert_eq!(
+            client.query_range(&owned, 0, 60, 15.0).build_url()?,
+            client.query_range("up", 0, 60, 15.0).build_url()?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_range_validation() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/query_range")
+            .match_query(mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        assert!(matches!(
+            client.query_range("up", 1700, 1600, 15.0).get().await,
+            Err(Error::InvalidTimeRange)
+        ));
+        assert!(matches!(
+            client.query_range("up", 1700, 1600, 15.0).post().await,
+            Err(Error::InvalidTimeRange)
+        ));
+        for step in [0.0, -15.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                client.query_range("up", 1600, 1700, step).get().await,
+                Err(Error::InvalidStep)
+            ));
+        }
+        assert!(matches!(
+            client.query_range("up", 1700, 1600, 15.0).build_url(),
+            Err(Error::InvalidTimeRange)
+        ));
+        // An empty time range is valid and yields at most one sample per series.
+        assert!(client
+            .query_range("up", 1600, 1600, 15.0)
+            .build_url()
+            .is_ok());
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tsdb_statistics_limit() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/status/tsdb")
+            .match_query(mockito::Matcher::UrlEncoded("limit".into(), "3".into()))
+            .with_header("content-type", "application/json")
+            .with_body(include_str!("../tests/fixtures/status_tsdb.json"))
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        let stats = client.tsdb_statistics_with_limit(3).await?;
+        assert_eq!(stats.head_stats().num_series(), 508);
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metric_names() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/label/__name__/values")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"status":"success","data":["go_goroutines","process_cpu_seconds_total","up"]}"#,
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        let expected = vec!["go_goroutines", "process_cpu_seconds_total", "up"];
+        assert_eq!(client.label_values("__name__").get().await?, expected);
+        assert_eq!(client.metric_names().get().await?, expected);
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_provider() -> Result<(), anyhow::Error> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let mut server = mockito::Server::new_async().await;
+        let body = r#"{"status":"success","data":["job"]}"#;
+        let mut mocks = vec![];
+        for token in ["Bearer token-1", "Bearer token-2"] {
+            let mock = server
+                .mock("GET", "/api/v1/labels")
+                .match_header("authorization", token)
+                .with_header("content-type", "application/json")
+                .with_body(body)
+                .expect(1)
+                .create_async()
+                .await;
+            mocks.push(mock);
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = |calls: Arc<AtomicUsize>| {
+            move || {
+                let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                async move { Ok(format!("token-{}", n)) }
+            }
+        };
+
+        // Without a time-to-live every request obtains a fresh token.
+        let client =
+            Client::try_from(server.url())?.bearer_token_provider(provider(calls.clone()), None);
+        client.label_names().get().await?;
+        client.label_names().get().await?;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+
+        // Within the time-to-live the token is reused.
+        let mock = server
+            .mock("GET", "/api/v1/labels")
+            .match_header("authorization", "Bearer token-1")
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .expect(2)
+            .create_async()
+            .await;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = Client::try_from(server.url())?
+            .bearer_token_provider(provider(calls.clone()), Duration::from_secs(60));
+        client.label_names().get().await?;
+        client.clone().label_names().get().await?;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        mock.assert_async().await;
+
+        // Errors of the provider are returned as-is.
+        let client = Client::try_from(server.url())?
+            .bearer_token_provider(|| async { Err(Error::MissingData) }, None);
+        assert!(matches!(
+            client.label_names().get().await,
+            Err(Error::MissingData)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_honors_retry_after() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let throttled = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::Any)
+            .with_status(429)
+            .with_header("retry-after", "2")
+            .expect(1)
+            .create_async()
+            .await;
+        let success = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?.retry(1, Duration::from_millis(10));
+        let start = std::time::Instant::now();
+        let result = client.query("up").get().await?;
+        let elapsed = start.elapsed();
+        assert!(result.data().as_vector().is_some());
+        assert!(elapsed >= Duration::from_secs(2), "waited {:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(5), "waited {:?}", elapsed);
+        throttled.assert_async().await;
+        success.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_ignores_overlong_retry_after() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let throttled = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::Any)
+            .with_status(503)
+            .with_header("retry-after", "86400")
+            .expect(1)
+            .create_async()
+            .await;
+        let success = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?.retry(1, Duration::from_millis(10));
+        let start = std::time::Instant::now();
+        let result = tokio::time::timeout(Duration::from_secs(5), client.query("up").get())
+            .await
+            .expect("the overlong Retry-After header was honored")?;
+        assert!(result.data().as_vector().is_some());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        throttled.assert_async().await;
+        success.assert_async().await;
+
+        // A lower cap also applies to waits that are otherwise acceptable.
+        let mut server = mockito::Server::new_async().await;
+        let throttled = server
+            .mock("GET", "/api/v1/labels")
+            .with_status(429)
+            .with_header("retry-after", "10")
+            .expect(1)
+            .create_async()
+            .await;
+        let success = server
+            .mock("GET", "/api/v1/labels")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":["job"]}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let client = Client::try_from(server.url())?
+            .retry(1, Duration::from_millis(10))
+            .max_retry_wait(Duration::from_secs(1));
+        let labels = tokio::time::timeout(Duration::from_secs(5), client.label_names().get())
+            .await
+            .expect("the Retry-After header exceeding the cap was honored")?;
+        assert_eq!(labels, vec!["job".to_string()]);
+        throttled.assert_async().await;
+        success.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_limits_attempts() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/query")
+            .with_status(503)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"error","errorType":"unavailable","error":"too many queries"}"#)
+            .expect(3)
+            .create_async()
+            .await;
+
+        // Without a Retry-After header the fixed backoff is used.
+        let client = Client::try_from(server.url())?.retry(2, Duration::from_millis(10));
+        match client.query("up").post().await {
+            Err(Error::Prometheus(e)) => assert!(e.is_unavailable()),
+            _ => panic!("expected an unavailable error"),
+        }
+        mock.assert_async().await;
+
+        // Requests are not retried by default.
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/labels")
+            .with_status(429)
+            .expect(1)
+            .create_async()
+            .await;
+        let client = Client::try_from(server.url())?;
+        assert!(client.label_names().get().await.is_err());
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_route_header() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let routed = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::Any)
+            .match_header("x-prometheus-backend", "replica-b")
+            .match_header("x-scope-orgid", "team-a")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let default = server
+            .mock("GET", "/api/v1/query_range")
+            .match_query(mockito::Matcher::Any)
+            .match_header("x-prometheus-backend", "replica-a")
+            .match_header("x-scope-orgid", "team-a")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"matrix","result":[]}}"#)
+            .create_async()
+            .await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-prometheus-backend",
+            HeaderValue::from_static("replica-a"),
+        );
+        headers.insert("x-scope-orgid", HeaderValue::from_static("team-a"));
+        let client = Client::builder()
+            .base_url(server.url())
+            .default_headers(headers)
+            .build()?;
+
+        client
+            .query("up")
+            .route_header(
+                "x-prometheus-backend",
+                HeaderValue::from_static("replica-b"),
+            )
+            .get()
+            .await?;
+        // The routing header replaces values added before.
+        client
+            .query("up")
+            .header(
+                "x-prometheus-backend",
+                HeaderValue::from_static("replica-c"),
+            )
+            .route_header(
+                "x-prometheus-backend",
+                HeaderValue::from_static("replica-b"),
+            )
+            .get()
+            .await?;
+        client.query_range("up", 0, 60, 15.0).get().await?;
+        routed.assert_async().await;
+        default.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_raw() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let instant = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("query".into(), "up".into()),
+                mockito::Matcher::UrlEncoded("time".into(), "1659182624".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[{"metric":{"__name__":"up"},"value":[1659182624,"1"]}]},"infos":["new field"]}"#)
+            .create_async()
+            .await;
+        let range = server
+            .mock("GET", "/api/v1/query_range")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"matrix","result":[]}}"#)
+            .create_async()
+            .await;
+        let failed = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::UrlEncoded("query".into(), "up{".into()))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"status":"error","errorType":"bad_data","error":"unexpected end of input"}"#,
+            )
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        let value = client.query_raw("up", Some(1659182624)).await?;
+        let result = value["data"]["result"].as_array().unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0]["metric"]["__name__"], "up");
+        assert_eq!(value["infos"][0], "new field");
+
+        let value = client.query_range_raw("up", 0, 60, 15.0).await?;
+        assert_eq!(value["data"]["resultType"], "matrix");
+        assert!(value["data"]["result"].is_array());
+
+        match client.query_raw("up{", None).await {
+            Err(Error::Prometheus(e)) => assert!(e.is_bad_data()),
+            _ => panic!("expected a bad_data error"),
+        }
+        instant.assert_async().await;
+        range.assert_async().await;
+        failed.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_range_max_points_guard() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/query_range")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"matrix","result":[]}}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        // One day at a resolution of 5 seconds yields 17281 points.
+        match client
+            .query_range("up", 0, 86400, 5.0)
+            .max_points_guard(11000)
+            .get()
+            .await
+        {
+            Err(Error::TooManyPoints(e)) => {
+                assert_eq!(e.requested(), 17281);
+                assert_eq!(e.max(), 11000);
+            }
+            _ => panic!("expected a too many points error"),
+        }
+        // 3601 points at a resolution of 24 seconds.
+        client
+            .query_range("up", 0, 86400, 24.0)
+            .max_points_guard(3601)
+            .get()
+            .await?;
+        assert!(matches!(
+            client
+                .query_range("up", 0, 86400, 24.0)
+                .max_points_guard(3600)
+                .build_url(),
+            Err(Error::TooManyPoints(_))
+        ));
+        // Without a guard the query is sent regardless of its size.
+        client.query_range("up", 0, 86400, 5.0).get().await?;
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_await_builders_directly() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let query = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[{"metric":{"__name__":"up"},"value":[1659182624,"1"]}]}}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let labels = server
+            .mock("GET", "/api/v1/labels")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":["__name__","job"]}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        let awaited = client.query("up").await?;
+        let explicit = client.query("up").get().await?;
+        assert_eq!(awaited.data().as_vector(), explicit.data().as_vector());
+        assert_eq!(
+            client.label_names().await?,
+            client.label_names().get().await?
+        );
+        query.assert_async().await;
+        labels.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_range_chunked() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let chunk = |start: &str, end: &str| {
+            mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("query".into(), "up".into()),
+                mockito::Matcher::UrlEncoded("start".into(), start.into()),
+                mockito::Matcher::UrlEncoded("end".into(), end.into()),
+                mockito::Matcher::UrlEncoded("step".into(), "30".into()),
+            ])
+        };
+        let first = server
+            .mock("GET", "/api/v1/query_range")
+            .match_query(chunk("0", "60"))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"matrix","result":[{"metric":{"job":"a"},"values":[[0,"1"],[30,"1"],[60,"1"]]}]}}"#)
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/api/v1/query_range")
+            .match_query(chunk("60", "120"))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"matrix","result":[{"metric":{"job":"b"},"values":[[90,"0"]]},{"metric":{"job":"a"},"values":[[60,"1"],[90,"0"],[120,"0"]]}]}}"#)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        let result = client
+            .query_range_chunked("up", 0, 120, 30.0, Duration::from_secs(60))
+            .await?;
+        let matrix = result.data().as_matrix().unwrap();
+        assert_eq!(matrix.len(), 2);
+        assert_eq!(matrix[0].metric().get("job"), Some("a"));
+        let timestamps: Vec<f64> = matrix[0].samples().iter().map(|s| s.timestamp()).collect();
+        assert_eq!(timestamps, vec![0.0, 30.0, 60.0, 90.0, 120.0]);
+        assert_eq!(matrix[1].metric().get("job"), Some("b"));
+        assert_eq!(matrix[1].samples().len(), 1);
+        first.assert_async().await;
+        second.assert_async().await;
+
+        assert!(matches!(
+            client
+                .query_range_chunked("up", 120, 0, 30.0, Duration::from_secs(60))
+                .await,
+            Err(Error::InvalidTimeRange)
+        ));
+        assert!(matches!(
+            client
+                .query_range_chunked("up", 0, 120, 0.0, Duration::from_secs(60))
+                .await,
+            Err(Error::InvalidStep)
+        ));
+
+        // A result that is not a matrix is reported instead of being dropped.
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/query_range")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#)
+            .create_async()
+            .await;
+        let client = Client::try_from(server.url())?;
+        match client
+            .query_range_chunked("up", 0, 120, 30.0, Duration::from_secs(60))
+            .await
+        {
+            Err(Error::Client(e)) => {
+                assert_eq!(e.to_string(), "expected a matrix result from a range query")
+            }
+            _ => panic!("expected a client error"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_data() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/query")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success"}"#)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        let error = client.query("up").get().await.unwrap_err();
+        assert!(matches!(error, Error::MissingData));
+        assert_eq!(
+            error.to_string(),
+            "the server returned a successful response without any data"
+        );
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[cfg(feature = "tracing")]
+    mod tracing {
+        use std::fmt::Write;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+        use tracing_subscriber::registry::LookupSpan;
+
+        /// Records the fields of all events as `name=value` strings.
+        #[derive(Clone, Default)]
+        pub(super) struct Recorder(pub(super) Arc<Mutex<Vec<String>>>);
+
+        struct Fields(String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                let _ = write!(self.0, "{}={:?} ", field.name(), value);
+            }
+        }
+
+        impl<S> Layer<S> for Recorder
+        where
+            S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+                let mut fields = Fields(String::new());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0.trim_end().to_string());
+            }
+
+            fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+                let mut fields = Fields(String::new());
+                attrs.record(&mut fields);
+                ctx.span(id).unwrap().extensions_mut().insert(fields);
+            }
+
+            fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+                let span = ctx.span(id).unwrap();
+                let mut extensions = span.extensions_mut();
+                values.record(extensions.get_mut::<Fields>().unwrap());
+            }
+
+            fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+                let span = ctx.span(&id).unwrap();
+                let extensions = span.extensions();
+                let fields = extensions.get::<Fields>().unwrap();
+                let closed = format!("span {}: {}", span.name(), fields.0.trim_end());
+                self.0.lock().unwrap().push(closed);
+            }
+        }
+
+        pub(super) fn subscriber(recorder: &Recorder) -> impl tracing::Subscriber {
+            tracing_subscriber::registry().with(recorder.clone())
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_request_emits_tracing_event() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/query")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#)
+            .create_async()
+            .await;
+
+        let recorder = tracing::Recorder::default();
+        let _guard = ::tracing::subscriber::set_default(tracing::subscriber(&recorder));
+
+        let client = Client::try_from(server.url())?;
+        client.query("up").post().await?;
+
+        let events = recorder.0.lock().unwrap();
+        let expected = format!(
+            "message=sending request to Prometheus method=POST url={}/api/v1/query",
+            server.url()
+        );
+        assert!(events.contains(&expected), "{:?}", events);
+        Ok(())
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_request_is_wrapped_in_tracing_span() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/series")
+            .match_query(mockito::Matcher::Any)
+            .with_status(503)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"error","errorType":"unavailable","error":"try again later"}"#)
+            .create_async()
+            .await;
+
+        let recorder = tracing::Recorder::default();
+        let _guard = ::tracing::subscriber::set_default(tracing::subscriber(&recorder));
+
+        let client = Client::try_from(server.url())?;
+        let result = client.series(&[Selector::new().metric("up")])?.get().await;
+        assert!(matches!(result, Err(Error::Prometheus(_))));
+
+        // Requests that cannot be sent record the error.
+        let client = Client::try_from("http://127.0.0.1:1")?;
+        assert!(client.flags().await.is_err());
+
+        let events = recorder.0.lock().unwrap();
+        let spans = events
+            .iter()
+            .filter(|e| e.starts_with("span prometheus_request: "))
+            .collect::<Vec<_>>();
+        assert_eq!(spans.len(), 2, "{:?}", events);
+        assert!(spans[0].starts_with(
+            "span prometheus_request: endpoint=\"api/v1/series\" method=GET elapsed_ms="
+        ));
+        assert!(spans[0].ends_with(" status=503"));
+        assert!(spans[1].starts_with(
+            "span prometheus_request: endpoint=\"api/v1/status/flags\" method=GET elapsed_ms="
+        ));
+        assert!(spans[1].contains(" error=failed to send request to server: "));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_range_fit() -> Result<(), anyhow::Error> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/query_range")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("start".into(), "1648373100".into()),
+                mockito::Matcher::UrlEncoded("end".into(), "1648376700".into()),
+                mockito::Matcher::UrlEncoded("step".into(), "15".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":"success","data":{"resultType":"matrix","result":[]}}"#)
+            .create_async()
+            .await;
+
+        let client = Client::try_from(server.url())?;
+        let (result, step) = client
+            .query_range_fit("up", 1648373100, 1648376700, 241)
+            .await?;
+        mock.assert_async().await;
+        assert!(result.data().as_matrix().is_some());
+        assert_eq!(step, Duration::from_secs(15));
+        Ok(())
+    }
+}
@@ -133,7 +133,10 @@ pub fn label_names(host: &str) -> Result<LabelNamesQueryBuilder, Error> {
 ///     Ok(())
 /// }
 /// ```
-pub fn label_values(host: &str, label: &str) -> Result<LabelValuesQueryBuilder, Error> {
+pub fn label_values(
+    host: &str,
+    label: impl std::fmt::Display,
+) -> Result<LabelValuesQueryBuilder, Error> {
     Client::from_str(host).map(|c| c.label_values(label))
 }
 
@@ -304,7 +307,7 @@ pub async fn alertmanagers(host: &str) -> Result<Alertmanagers, Error> {
 ///     Ok(())
 /// }
 /// ```
-pub fn target_metadata(host: &str) -> Result<TargetMetadataQueryBuilder, Error> {
+pub fn target_metadata(host: &str) -> Result<TargetMetadataQueryBuilder<'_>, Error> {
     Client::from_str(host).map(|c| c.target_metadata())
 }
 
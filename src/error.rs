@@ -22,6 +22,26 @@ pub enum Error {
     EmptySeriesSelector,
     /// Wraps errors from the [`url`] crate.
     ParseUrl(ParseUrlError),
+    /// Occurs when a PromQL function is built from an invalid argument, e.g. a
+    /// range vector selector with a malformed time window.
+    InvalidFunctionArgument(InvalidFunctionArgumentError),
+    /// Occurs when the response contains warnings while
+    /// [`Client::fail_on_warnings`](crate::Client::fail_on_warnings) is enabled, e.g. because
+    /// only a partial response could be retrieved. Contains the warnings as reported by the server.
+    PartialResponse(Vec<String>),
+    /// Occurs when the server reports success but the response lacks the `data` field,
+    /// which is e.g. the case with some misbehaving proxies.
+    MissingData,
+    /// Occurs when a range query is built with a start timestamp that lies after the end timestamp.
+    InvalidTimeRange,
+    /// Occurs when a range query is built with a step width that is not a positive number.
+    InvalidStep,
+    /// Occurs when a range query with [`RangeQueryBuilder::max_points_guard`](crate::RangeQueryBuilder::max_points_guard)
+    /// would return more data points per time series than allowed.
+    TooManyPoints(TooManyPointsError),
+    /// Occurs when [`validate_query`](crate::validate_query) detects an obvious syntax error
+    /// in a PromQL query.
+    InvalidQuery(InvalidQueryError),
 }
 
 impl fmt::Display for Error {
@@ -31,6 +51,23 @@ impl fmt::Display for Error {
             Self::Prometheus(e) => e.fmt(f),
             Self::EmptySeriesSelector => f.write_str("at least one series selector must be provided in order to query the series endpoint"),
             Self::ParseUrl(e) => e.fmt(f),
+            Self::InvalidFunctionArgument(e) => e.fmt(f),
+            Self::PartialResponse(warnings) => write!(
+                f,
+                "the server returned a response with warnings: {}",
+                warnings.join("; ")
+            ),
+            Self::MissingData => {
+                f.write_str("the server returned a successful response without any data")
+            }
+            Self::InvalidTimeRange => f.write_str(
+                "the start timestamp of a range query must not be greater than the end timestamp",
+            ),
+            Self::InvalidStep => {
+                f.write_str("the step width of a range query must be a positive number")
+            }
+            Self::TooManyPoints(e) => e.fmt(f),
+            Self::InvalidQuery(e) => e.fmt(f),
         }
     }
 }
@@ -42,10 +79,74 @@ impl StdError for Error {
             Self::Prometheus(_) => None,
             Self::EmptySeriesSelector => None,
             Self::ParseUrl(e) => e.source(),
+            Self::InvalidFunctionArgument(_) => None,
+            Self::PartialResponse(_) => None,
+            Self::MissingData => None,
+            Self::InvalidTimeRange => None,
+            Self::InvalidStep => None,
+            Self::TooManyPoints(_) => None,
+            Self::InvalidQuery(_) => None,
         }
     }
 }
 
+impl From<ClientError> for Error {
+    fn from(error: ClientError) -> Self {
+        Self::Client(error)
+    }
+}
+
+impl From<PrometheusError> for Error {
+    fn from(error: PrometheusError) -> Self {
+        Self::Prometheus(error)
+    }
+}
+
+impl From<TooManyPointsError> for Error {
+    fn from(error: TooManyPointsError) -> Self {
+        Self::TooManyPoints(error)
+    }
+}
+
+impl From<InvalidQueryError> for Error {
+    fn from(error: InvalidQueryError) -> Self {
+        Self::InvalidQuery(error)
+    }
+}
+
+impl From<ParseUrlError> for Error {
+    fn from(error: ParseUrlError) -> Self {
+        Self::ParseUrl(error)
+    }
+}
+
+impl From<InvalidFunctionArgumentError> for Error {
+    fn from(error: InvalidFunctionArgumentError) -> Self {
+        Self::InvalidFunctionArgument(error)
+    }
+}
+
+/// Wraps the [`reqwest::Error`] in an [`Error::Client`], e.g. when a request
+/// to Prometheus is built and sent using [`Client::inner`](crate::Client::inner).
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Client(ClientError {
+            message: "failed to execute request",
+            source: Some(error),
+        })
+    }
+}
+
+/// Wraps the [`url::ParseError`] in an [`Error::ParseUrl`].
+impl From<url::ParseError> for Error {
+    fn from(error: url::ParseError) -> Self {
+        Self::ParseUrl(ParseUrlError {
+            message: "failed to parse URL",
+            source: error,
+        })
+    }
+}
+
 /// This error is thrown when the JSON response's `status` field contains `error`.<br>
 /// The error-related information from the JSON body is included in this error.
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -148,7 +249,7 @@ pub struct ClientError {
 
 impl fmt::Display for ClientError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(&self.message)
+        f.write_str(self.message)
     }
 }
 
@@ -177,7 +278,7 @@ pub struct ParseUrlError {
 
 impl fmt::Display for ParseUrlError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(&self.message)
+        f.write_str(self.message)
     }
 }
 
@@ -193,3 +294,156 @@ impl ParseUrlError {
         &self.source
     }
 }
+
+/// Is thrown when a PromQL function is built from an invalid argument.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidFunctionArgumentError {
+    pub(crate) message: &'static str,
+}
+
+impl fmt::Display for InvalidFunctionArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
+impl StdError for InvalidFunctionArgumentError {}
+
+/// Is thrown when a range query would return more data points per time series than
+/// allowed by [`RangeQueryBuilder::max_points_guard`](crate::RangeQueryBuilder::max_points_guard).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TooManyPointsError {
+    pub(crate) requested: u64,
+    pub(crate) max: u64,
+}
+
+impl fmt::Display for TooManyPointsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the range query would return {} points per time series, but at most {} are allowed, consider increasing the step width",
+            self.requested, self.max
+        )
+    }
+}
+
+impl StdError for TooManyPointsError {}
+
+impl TooManyPointsError {
+    /// Returns the number of points per time series that the query would return.
+    pub fn requested(&self) -> u64 {
+        self.requested
+    }
+
+    /// Returns the maximum number of points per time series that is allowed.
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+}
+
+/// Is thrown when [`validate_query`](crate::validate_query) detects an obvious syntax
+/// error in a PromQL query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidQueryError {
+    pub(crate) message: &'static str,
+    pub(crate) position: usize,
+}
+
+impl fmt::Display for InvalidQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl StdError for InvalidQueryError {}
+
+impl InvalidQueryError {
+    /// Returns a short description of the syntax error.
+    pub fn message(&self) -> &str {
+        self.message
+    }
+
+    /// Returns the byte offset within the query at which the syntax error was detected.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_error_source() {
+        let source = reqwest::Client::new().get("http://").build().unwrap_err();
+        let err = Error::Client(ClientError {
+            message: "failed to build request",
+            source: Some(source),
+        });
+        let source = err.source();
+        assert!(source.is_some());
+        assert!(source.unwrap().is::<reqwest::Error>());
+    }
+
+    #[test]
+    fn test_client_error_without_source() {
+        let err = Error::Client(ClientError {
+            message: "failed to parse response from server due to invalid media type",
+            source: None,
+        });
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn test_parse_url_error_source() {
+        let source = url::Url::parse("not a url").unwrap_err();
+        let err = Error::ParseUrl(ParseUrlError {
+            message: "failed to build Prometheus server base URL",
+            source,
+        });
+        assert!(err.source().is_some_and(|e| e.is::<url::ParseError>()));
+    }
+
+    #[test]
+    fn test_from_conversions() {
+        fn build_request() -> Result<reqwest::Request, Error> {
+            Ok(reqwest::Client::new().get("http://").build()?)
+        }
+
+        fn parse_url() -> Result<url::Url, Error> {
+            Ok(url::Url::parse("not a url")?)
+        }
+
+        let err = build_request().unwrap_err();
+        assert!(matches!(&err, Error::Client(e) if e.inner().is_some()));
+        assert!(err.source().is_some_and(|e| e.is::<reqwest::Error>()));
+
+        let err = parse_url().unwrap_err();
+        assert!(
+            matches!(err, Error::ParseUrl(e) if e.inner() == &url::ParseError::RelativeUrlWithoutBase)
+        );
+
+        let err: Error = InvalidFunctionArgumentError { message: "invalid" }.into();
+        assert!(matches!(err, Error::InvalidFunctionArgument(_)));
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn _assert_send_sync<T: Send + Sync + 'static>() {}
+
+        _assert_send_sync::<Error>();
+        _assert_send_sync::<PrometheusError>();
+        _assert_send_sync::<ClientError>();
+        _assert_send_sync::<ParseUrlError>();
+        _assert_send_sync::<InvalidFunctionArgumentError>();
+        _assert_send_sync::<TooManyPointsError>();
+        _assert_send_sync::<InvalidQueryError>();
+
+        let source = reqwest::Client::new().get("http://").build().unwrap_err();
+        let err: Box<dyn StdError + Send + Sync> = Box::new(Error::Client(ClientError {
+            message: "failed to build request",
+            source: Some(source),
+        }));
+        assert!(err.source().is_some());
+    }
+}
@@ -0,0 +1,522 @@
+//! Helpers to build PromQL [function](https://prometheus.io/docs/prometheus/latest/querying/functions/) calls.
+//!
+//! Each function takes one or more PromQL expressions (anything that implements [`Display`],
+//! e.g. a [`Selector`](crate::Selector) or the output of another function) along with the
+//! function's parameters and returns the rendered expression. String parameters are quoted
+//! and escaped automatically.
+//!
+//! ```rust
+//! use prometheus_http_query::{functions, Selector};
+//!
+//! let select = Selector::new().metric("node_memory_MemAvailable_bytes");
+//!
+//! let query = functions::round(functions::clamp_min(&select, 0.0), Some(1024.0));
+//!
+//! let expected = r#"round(clamp_min({__name__="node_memory_MemAvailable_bytes"}, 0), 1024)"#;
+//!
+//! assert_eq!(query, expected);
+//! ```
+use crate::error::{Error, InvalidFunctionArgumentError};
+use crate::util::quote;
+use std::fmt::Display;
+
+/// Join a list of label names as quoted PromQL strings.
+fn quote_all<I>(labels: I) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    labels
+        .into_iter()
+        .map(|l| quote(l.as_ref()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Clamp the sample values of all elements in `vector` to a lower limit of `min`
+/// and an upper limit of `max`.<br>
+/// PromQL equivalent: `clamp(v, min, max)`
+///
+/// ```rust
+/// use prometheus_http_query::{functions, Selector};
+///
+/// let query = functions::clamp(Selector::new().metric("temperature"), -20.0, 45.5);
+///
+/// assert_eq!(query, r#"clamp({__name__="temperature"}, -20, 45.5)"#);
+/// ```
+pub fn clamp(vector: impl Display, min: f64, max: f64) -> String {
+    format!("clamp({}, {}, {})", vector, min, max)
+}
+
+/// Clamp the sample values of all elements in `vector` to a lower limit of `min`.<br>
+/// PromQL equivalent: `clamp_min(v, min)`
+pub fn clamp_min(vector: impl Display, min: f64) -> String {
+    format!("clamp_min({}, {})", vector, min)
+}
+
+/// Clamp the sample values of all elements in `vector` to an upper limit of `max`.<br>
+/// PromQL equivalent: `clamp_max(v, max)`
+pub fn clamp_max(vector: impl Display, max: f64) -> String {
+    format!("clamp_max({}, {})", vector, max)
+}
+
+/// Calculate the φ-quantile (0 ≤ φ ≤ 1) from the buckets of a histogram.<br>
+/// PromQL equivalent: `histogram_quantile(φ, b)`
+///
+/// For classic histograms `buckets` must retain the `le` label, e.g. by aggregating
+/// with [`sum_by`] and `le`. Native histograms carry their buckets in each sample,
+/// so they may be aggregated without regard to `le`.<br>
+/// Returns an error if `phi` is not within the range of 0 to 1.
+///
+/// ```rust
+/// use prometheus_http_query::functions;
+///
+/// // A classic histogram
+/// let query = functions::histogram_quantile(
+///     0.9,
+///     functions::sum_by("rate(http_request_duration_seconds_bucket[10m])", ["le"]),
+/// )?;
+///
+/// assert_eq!(
+///     query,
+///     "histogram_quantile(0.9, sum by (le) (rate(http_request_duration_seconds_bucket[10m])))"
+/// );
+///
+/// // A native histogram
+/// let query = functions::histogram_quantile(
+///     0.9,
+///     functions::sum("rate(http_request_duration_seconds[10m])"),
+/// )?;
+///
+/// assert_eq!(
+///     query,
+///     "histogram_quantile(0.9, sum(rate(http_request_duration_seconds[10m])))"
+/// );
+///
+/// assert!(functions::histogram_quantile(99.0, "http_request_duration_seconds").is_err());
+/// # Ok::<(), prometheus_http_query::Error>(())
+/// ```
+pub fn histogram_quantile(phi: f64, buckets: impl Display) -> Result<String, Error> {
+    if !(0.0..=1.0).contains(&phi) {
+        return Err(Error::InvalidFunctionArgument(
+            InvalidFunctionArgumentError {
+                message: "the quantile of histogram_quantile must be a number between 0 and 1",
+            },
+        ));
+    }
+    Ok(format!("histogram_quantile({}, {})", phi, buckets))
+}
+
+/// Calculate the sum over all elements of `vector`.<br>
+/// PromQL equivalent: `sum(v)`
+pub fn sum(vector: impl Display) -> String {
+    format!("sum({})", vector)
+}
+
+/// Calculate the sum over the elements of `vector` that share the values of the given labels,
+/// i.e. preserving the labels.<br>
+/// PromQL equivalent: `sum by (labels) (v)`
+///
+/// ```rust
+/// use prometheus_http_query::{functions, Selector};
+///
+/// let select = Selector::new().metric("node_cpu_seconds_total");
+///
+/// let query = functions::sum_by(&select, ["instance", "mode"]);
+///
+/// assert_eq!(query, r#"sum by (instance, mode) ({__name__="node_cpu_seconds_total"})"#);
+/// ```
+pub fn sum_by<I>(vector: impl Display, labels: I) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let labels = labels
+        .into_iter()
+        .map(|l| l.as_ref().to_string())
+        .collect::<Vec<String>>();
+    format!("sum by ({}) ({})", labels.join(", "), vector)
+}
+
+/// Count the number of elements of `vector` that share the same sample value. The output
+/// contains one time series per distinct value, carrying the value in the label `label`.<br>
+/// PromQL equivalent: `count_values("label", v)`
+///
+/// ```rust
+/// use prometheus_http_query::{functions, Selector};
+///
+/// let select = Selector::new().metric("build_info");
+///
+/// let query = functions::count_values("version", &select);
+///
+/// assert_eq!(query, r#"count_values("version", {__name__="build_info"})"#);
+/// ```
+pub fn count_values(label: &str, vector: impl Display) -> String {
+    format!("count_values({}, {})", quote(label), vector)
+}
+
+/// For each time series in `vector` match the regular expression `regex` against
+/// the value of the label `src_label`. If it matches, the label `dst_label` is set
+/// to `replacement`, which may refer to capturing groups of the regular expression
+/// (e.g. `$1`).<br>
+/// PromQL equivalent: `label_replace(v, dst_label, replacement, src_label, regex)`
+///
+/// ```rust
+/// use prometheus_http_query::{functions, Selector};
+///
+/// let query = functions::label_replace(
+///     Selector::new().metric("up"),
+///     "host",
+///     "$1",
+///     "instance",
+///     "(.*):.*",
+/// );
+///
+/// assert_eq!(
+///     query,
+///     r#"label_replace({__name__="up"}, "host", "$1", "instance", "(.*):.*")"#
+/// );
+/// ```
+pub fn label_replace(
+    vector: impl Display,
+    dst_label: &str,
+    replacement: &str,
+    src_label: &str,
+    regex: &str,
+) -> String {
+    format!(
+        "label_replace({}, {}, {}, {}, {})",
+        vector,
+        quote(dst_label),
+        quote(replacement),
+        quote(src_label),
+        quote(regex)
+    )
+}
+
+/// For each time series in `vector` join the values of all `src_labels` using
+/// `separator` and store the result in the label `dst_label`.<br>
+/// PromQL equivalent: `label_join(v, dst_label, separator, src_label_1, src_label_2, ...)`
+///
+/// ```rust
+/// use prometheus_http_query::{functions, Selector};
+///
+/// let query = functions::label_join(
+///     Selector::new().metric("up"),
+///     "target",
+///     "/",
+///     ["job", "instance"],
+/// );
+///
+/// assert_eq!(query, r#"label_join({__name__="up"}, "target", "/", "job", "instance")"#);
+/// ```
+pub fn label_join<I>(
+    vector: impl Display,
+    dst_label: &str,
+    separator: &str,
+    src_labels: I,
+) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let src_labels = quote_all(src_labels);
+    let mut args = vec![vector.to_string(), quote(dst_label), quote(separator)];
+    if !src_labels.is_empty() {
+        args.push(src_labels);
+    }
+    format!("label_join({})", args.join(", "))
+}
+
+/// Sort the elements of `vector` by their sample values in ascending order.<br>
+/// PromQL equivalent: `sort(v)`
+pub fn sort(vector: impl Display) -> String {
+    format!("sort({})", vector)
+}
+
+/// Sort the elements of `vector` by their sample values in descending order.<br>
+/// PromQL equivalent: `sort_desc(v)`
+///
+/// ```rust
+/// use prometheus_http_query::functions;
+///
+/// let query = functions::sort_desc("topk(5, sum by (handler) (rate(http_requests_total[5m])))");
+///
+/// assert_eq!(query, "sort_desc(topk(5, sum by (handler) (rate(http_requests_total[5m]))))");
+/// ```
+pub fn sort_desc(vector: impl Display) -> String {
+    format!("sort_desc({})", vector)
+}
+
+/// Sort the elements of `vector` by the values of the given labels in ascending order.<br>
+/// PromQL equivalent: `sort_by_label(v, label, ...)`
+///
+/// ```rust
+/// use prometheus_http_query::{functions, Selector};
+///
+/// let query = functions::sort_by_label(Selector::new().metric("up"), ["job", "instance"]);
+///
+/// assert_eq!(query, r#"sort_by_label({__name__="up"}, "job", "instance")"#);
+/// ```
+pub fn sort_by_label<I>(vector: impl Display, labels: I) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    format!("sort_by_label({}, {})", vector, quote_all(labels))
+}
+
+/// Sort the elements of `vector` by the values of the given labels in descending order.<br>
+/// PromQL equivalent: `sort_by_label_desc(v, label, ...)`
+pub fn sort_by_label_desc<I>(vector: impl Display, labels: I) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    format!("sort_by_label_desc({}, {})", vector, quote_all(labels))
+}
+
+/// Round the sample values of all elements in `vector` to the nearest integer, or
+/// to the nearest multiple of `to_nearest` if given.<br>
+/// PromQL equivalent: `round(v, to_nearest)`
+pub fn round(vector: impl Display, to_nearest: Option<f64>) -> String {
+    match to_nearest {
+        Some(to_nearest) => format!("round({}, {})", vector, to_nearest),
+        None => format!("round({})", vector),
+    }
+}
+
+/// Return a 1-element vector with the value 1 if `vector` has no elements, otherwise
+/// return an empty vector. This is useful to alert on missing time series.<br>
+/// PromQL equivalent: `absent(v)`
+///
+/// See also: [`Selector::absent_over_time`](crate::Selector::absent_over_time)
+pub fn absent(vector: impl Display) -> String {
+    format!("absent({})", vector)
+}
+
+/// Return the timestamp (Unix timestamp in seconds) of the samples of all elements in `vector`.<br>
+/// PromQL equivalent: `timestamp(v)`
+pub fn timestamp(vector: impl Display) -> String {
+    format!("timestamp({})", vector)
+}
+
+/// Interpret the sample values of all elements in `vector` as Unix timestamps and return
+/// the day of the week (0 for Sunday to 6 for Saturday) of each timestamp in UTC.<br>
+/// PromQL equivalent: `day_of_week(v)`
+///
+/// Prometheus always evaluates these functions in UTC. Pass `"vector(time())"` as `vector`
+/// to use the evaluation time of the query. See [`Sample::value_as_datetime`](crate::response::Sample::value_as_datetime)
+/// to convert timestamps to a local time zone instead.
+///
+/// ```rust
+/// use prometheus_http_query::functions;
+///
+/// assert_eq!(functions::day_of_week("vector(time())"), "day_of_week(vector(time()))");
+/// assert_eq!(
+///     functions::day_of_week(functions::timestamp("up")),
+///     "day_of_week(timestamp(up))"
+/// );
+/// ```
+pub fn day_of_week(vector: impl Display) -> String {
+    format!("day_of_week({})", vector)
+}
+
+/// Interpret the sample values of all elements in `vector` as Unix timestamps and return
+/// the number of days in the month (28 to 31) of each timestamp in UTC.<br>
+/// PromQL equivalent: `days_in_month(v)`
+///
+/// See [`day_of_week`] for details.
+pub fn days_in_month(vector: impl Display) -> String {
+    format!("days_in_month({})", vector)
+}
+
+/// Interpret the sample values of all elements in `vector` as Unix timestamps and return
+/// the hour of the day (0 to 23) of each timestamp in UTC.<br>
+/// PromQL equivalent: `hour(v)`
+///
+/// See [`day_of_week`] for details.
+pub fn hour(vector: impl Display) -> String {
+    format!("hour({})", vector)
+}
+
+/// Interpret the sample values of all elements in `vector` as Unix timestamps and return
+/// the minute of the hour (0 to 59) of each timestamp in UTC.<br>
+/// PromQL equivalent: `minute(v)`
+///
+/// See [`day_of_week`] for details.
+pub fn minute(vector: impl Display) -> String {
+    format!("minute({})", vector)
+}
+
+/// Interpret the sample values of all elements in `vector` as Unix timestamps and return
+/// the month of the year (1 to 12) of each timestamp in UTC.<br>
+/// PromQL equivalent: `month(v)`
+///
+/// See [`day_of_week`] for details.
+pub fn month(vector: impl Display) -> String {
+    format!("month({})", vector)
+}
+
+/// Interpret the sample values of all elements in `vector` as Unix timestamps and return
+/// the year of each timestamp in UTC.<br>
+/// PromQL equivalent: `year(v)`
+///
+/// See [`day_of_week`] for details.
+pub fn year(vector: impl Display) -> String {
+    format!("year({})", vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Selector;
+
+    #[test]
+    fn test_clamp_functions() {
+        let s = Selector::new().metric("up");
+        assert_eq!(clamp(&s, 0.0, 1.0), r#"clamp({__name__="up"}, 0, 1)"#);
+        assert_eq!(clamp_min(&s, -0.5), r#"clamp_min({__name__="up"}, -0.5)"#);
+        assert_eq!(clamp_max(&s, 100.0), r#"clamp_max({__name__="up"}, 100)"#);
+    }
+
+    #[test]
+    fn test_histogram_quantile() {
+        // Classic histogram
+        let rate = "rate(http_request_duration_seconds_bucket[5m])";
+        assert_eq!(
+            histogram_quantile(0.99, sum_by(rate, ["le"])).unwrap(),
+            "histogram_quantile(0.99, sum by (le) (rate(http_request_duration_seconds_bucket[5m])))"
+        );
+        assert_eq!(
+            histogram_quantile(0.5, sum_by(rate, ["job", "le"])).unwrap(),
+            "histogram_quantile(0.5, sum by (job, le) (rate(http_request_duration_seconds_bucket[5m])))"
+        );
+        // Native histogram
+        let rate = "rate(http_request_duration_seconds[5m])";
+        assert_eq!(
+            histogram_quantile(0.99, sum(rate)).unwrap(),
+            "histogram_quantile(0.99, sum(rate(http_request_duration_seconds[5m])))"
+        );
+        assert_eq!(
+            histogram_quantile(1.0, sum_by(rate, ["job"])).unwrap(),
+            "histogram_quantile(1, sum by (job) (rate(http_request_duration_seconds[5m])))"
+        );
+        assert_eq!(
+            histogram_quantile(0.0, rate).unwrap(),
+            "histogram_quantile(0, rate(http_request_duration_seconds[5m]))"
+        );
+        for phi in [-0.1, 1.01, 99.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                histogram_quantile(phi, rate),
+                Err(Error::InvalidFunctionArgument(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_sum_functions() {
+        let s = Selector::new().metric("up");
+        assert_eq!(sum(&s), r#"sum({__name__="up"})"#);
+        assert_eq!(sum_by(&s, ["job"]), r#"sum by (job) ({__name__="up"})"#);
+        assert_eq!(
+            sum_by(&s, vec![String::from("job"), String::from("instance")]),
+            r#"sum by (job, instance) ({__name__="up"})"#
+        );
+        assert_eq!(sum_by(&s, [""; 0]), r#"sum by () ({__name__="up"})"#);
+    }
+
+    #[test]
+    fn test_count_values() {
+        assert_eq!(
+            count_values("version", "build_info"),
+            r#"count_values("version", build_info)"#
+        );
+        let s = Selector::new().metric("build_info").eq("job", "node");
+        assert_eq!(
+            count_values("version", sum_by(&s, ["version"])),
+            r#"count_values("version", sum by (version) ({__name__="build_info",job="node"}))"#
+        );
+        // The label name is a string literal and thus escaped.
+        assert_eq!(count_values(r#"a"b"#, "x"), r#"count_values("a\"b", x)"#);
+    }
+
+    #[test]
+    fn test_label_functions() {
+        let s = Selector::new().metric("up").eq("job", "node");
+        assert_eq!(
+            label_replace(&s, "port", "$2", "instance", r#"(.*):(\d+)"#),
+            r#"label_replace({__name__="up",job="node"}, "port", "$2", "instance", "(.*):(\\d+)")"#
+        );
+        assert_eq!(
+            label_join(
+                &s,
+                "id",
+                "-",
+                vec![String::from("job"), String::from("instance")]
+            ),
+            r#"label_join({__name__="up",job="node"}, "id", "-", "job", "instance")"#
+        );
+        assert_eq!(
+            label_join(&s, "empty", "", [""; 0]),
+            r#"label_join({__name__="up",job="node"}, "empty", "")"#
+        );
+        assert_eq!(
+            label_replace("up", "quoted", r#"a "b""#, "job", ".*"),
+            r#"label_replace(up, "quoted", "a \"b\"", "job", ".*")"#
+        );
+    }
+
+    #[test]
+    fn test_sort_functions() {
+        let s = Selector::new().metric("up");
+        assert_eq!(sort(&s), r#"sort({__name__="up"})"#);
+        assert_eq!(sort_desc(&s), r#"sort_desc({__name__="up"})"#);
+        assert_eq!(
+            sort_by_label("metric", ["instance"]),
+            r#"sort_by_label(metric, "instance")"#
+        );
+        assert_eq!(
+            sort_by_label_desc(&s, ["job", "instance"]),
+            r#"sort_by_label_desc({__name__="up"}, "job", "instance")"#
+        );
+        assert_eq!(sort_desc("topk(3, up)"), "sort_desc(topk(3, up))");
+    }
+
+    #[test]
+    fn test_sort_round_absent() {
+        let s = Selector::new().metric("up");
+        assert_eq!(
+            sort_by_label(&s, &["instance"]),
+            r#"sort_by_label({__name__="up"}, "instance")"#
+        );
+        assert_eq!(round(&s, None), r#"round({__name__="up"})"#);
+        assert_eq!(round(&s, Some(0.5)), r#"round({__name__="up"}, 0.5)"#);
+        assert_eq!(absent(&s), r#"absent({__name__="up"})"#);
+        assert_eq!(
+            absent(s.absent_over_time("1h").unwrap()),
+            r#"absent(absent_over_time({__name__="up"}[1h]))"#
+        );
+    }
+
+    #[test]
+    fn test_time_functions() {
+        let s = Selector::new().metric("node_boot_time_seconds");
+        assert_eq!(
+            timestamp(&s),
+            r#"timestamp({__name__="node_boot_time_seconds"})"#
+        );
+        assert_eq!(
+            day_of_week(&s),
+            r#"day_of_week({__name__="node_boot_time_seconds"})"#
+        );
+        assert_eq!(
+            days_in_month("vector(time())"),
+            "days_in_month(vector(time()))"
+        );
+        assert_eq!(hour(timestamp("up")), "hour(timestamp(up))");
+        assert_eq!(minute("vector(1700000000)"), "minute(vector(1700000000))");
+        assert_eq!(month(&s), r#"month({__name__="node_boot_time_seconds"})"#);
+        assert_eq!(year(&s), r#"year({__name__="node_boot_time_seconds"})"#);
+    }
+}
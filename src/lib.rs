@@ -141,7 +141,7 @@
 //!
 //! # Features
 //!
-//! At this point all available feature flags pertain to the [`Client`]s TLS configuration. They enable feature flags of
+//! Most feature flags pertain to the [`Client`]s TLS configuration. They enable feature flags of
 //! the `reqwest` crate by the same name.<br>
 //! See the [reqwest documentation](https://docs.rs/reqwest/0.11.14/reqwest/index.html#optional-features) for details on
 //! these feature flags.<br>
@@ -150,6 +150,17 @@
 //!
 //! `prometheus-http-query = { version = "0.7", default-features = false, features = ["rustls-tls"] }`
 //!
+//! The default feature `default-tls` uses the platform's native TLS library. Choosing one of the `rustls-tls`
+//! features instead avoids linking against OpenSSL, e.g. for static builds targeting musl.
+//!
+//! Additional feature flags:
+//! - `table`: enables [`response::PromqlResult::to_table`] to render query results as plain text tables.
+//! - `cache`: enables `Client::with_cache` to cache the responses of metadata queries in memory.
+//! - `tracing`: wraps each request in a `prometheus_request` span using the [`tracing`](https://docs.rs/tracing)
+//!   crate. The span records the API endpoint, the HTTP method, the response status code and the time
+//!   elapsed until the response was received, or the error that occurred while sending the request.
+//!   Additionally a debug event containing the final URL is emitted.
+//!
 //! # Compatibility
 //!
 //! The crate is generally compatible with Prometheus server >=2.30. However individual [`Client`] methods might
@@ -166,14 +177,14 @@
 //! may fail at different stages. In general the following approach is taken to return the most significant
 //! error to the caller:
 //! - When the server's response contains header `Content-Type: application/json` (or variants thereof) the
-//! JSON body is parsed to the target type, regardless of the HTTP status code, since Prometheus returns elaborate
-//! error messages within the HTTP body in any case.
-//! A JSON response having `"status": "success"` is deserialized to the target type of this function and returned
-//! within `Result::Ok`. A response with `"status": "error"` is instead deserialized to a [`error::PrometheusError`]
-//! and returned within `Result::Err`.
+//!   JSON body is parsed to the target type, regardless of the HTTP status code, since Prometheus returns elaborate
+//!   error messages within the HTTP body in any case.
+//!   A JSON response having `"status": "success"` is deserialized to the target type of this function and returned
+//!   within `Result::Ok`. A response with `"status": "error"` is instead deserialized to a [`error::PrometheusError`]
+//!   and returned within `Result::Err`.
 //! - Any other server HTTP 4xx/5xx responses without the proper header indicating a JSON-encoded body are
-//! returned as [`Error::Client`] within `Result::Err`. For example, this may happen when an intermediate proxy server
-//! fails to handle a request and subsequently return a plain text error message and a non-2xx HTTP status code.
+//!   returned as [`Error::Client`] within `Result::Err`. For example, this may happen when an intermediate proxy server
+//!   fails to handle a request and subsequently return a plain text error message and a non-2xx HTTP status code.
 //!
 //! # Supported operations
 //!
@@ -197,21 +208,29 @@
 //!
 //! * Some [`Client`] methods may not work with older versions of the Prometheus server.
 //! * The [String](https://prometheus.io/docs/prometheus/latest/querying/api/#strings) result type is not supported
-//! as it is currently not used by Prometheus.
-//! * Warnings contained in an API response will be ignored.
+//!   as it is currently not used by Prometheus.
+//! * Warnings contained in an API response will be ignored, unless [`Client::fail_on_warnings`] is enabled.
+mod auth;
+#[cfg(feature = "cache")]
+mod cache;
 mod client;
 mod direct;
 pub mod error;
+pub mod functions;
 pub mod response;
 mod selector;
 mod util;
 pub use self::client::{
-    Client, InstantQueryBuilder, LabelNamesQueryBuilder, LabelValuesQueryBuilder,
+    Client, ClientBuilder, InstantQueryBuilder, LabelNamesQueryBuilder, LabelValuesQueryBuilder,
     MetricMetadataQueryBuilder, RangeQueryBuilder, RulesQueryBuilder, SeriesQueryBuilder,
     TargetMetadataQueryBuilder,
 };
 pub use self::direct::*;
 pub use self::error::Error;
 pub use self::selector::Selector;
+pub use self::util::escape_label_value;
+pub use self::util::validate_query;
+pub use self::util::AlertState;
 pub use self::util::RuleKind;
 pub use self::util::TargetState;
+pub use self::util::Timestamp;
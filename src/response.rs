@@ -1,10 +1,10 @@
 //! All types that are returned when querying the Prometheus API.
-use crate::util::{AlertState, RuleHealth, TargetHealth};
+use crate::util::{AlertState, RuleHealth, TargetHealth, Timestamp};
 use enum_as_inner::EnumAsInner;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{btree_map, BTreeMap, HashMap};
 use std::fmt;
-use time::{Duration, OffsetDateTime, PrimitiveDateTime};
+use time::{Duration, OffsetDateTime, PrimitiveDateTime, UtcOffset};
 use url::Url;
 
 mod de {
@@ -129,7 +129,12 @@ mod de {
 #[serde(tag = "status")]
 pub(crate) enum ApiResponse<D> {
     #[serde(alias = "success")]
-    Success { data: D },
+    Success {
+        // Optional so that a missing field can be reported as `Error::MissingData`.
+        data: Option<D>,
+        #[serde(default)]
+        warnings: Vec<String>,
+    },
     #[serde(alias = "error")]
     Error(crate::error::PrometheusError),
 }
@@ -221,6 +226,8 @@ pub struct PromqlResult {
     #[serde(flatten)]
     pub(crate) data: Data,
     pub(crate) stats: Option<Stats>,
+    #[serde(skip)]
+    pub(crate) evaluation_time: Option<i64>,
 }
 
 impl PromqlResult {
@@ -234,13 +241,340 @@ impl PromqlResult {
         self.stats.as_ref()
     }
 
+    /// Return the evaluation timestamp (Unix timestamp in seconds) that was requested via
+    /// [`InstantQueryBuilder::at`](crate::InstantQueryBuilder::at), or `None` if the query was
+    /// evaluated at the current Prometheus server time.<br>
+    /// Note that the timestamps of the returned samples may be earlier than the evaluation
+    /// time, as Prometheus selects the most recent sample within the lookback period.
+    pub fn evaluation_time(&self) -> Option<i64> {
+        self.evaluation_time
+    }
+
+    /// Consume this result and return the owned response [`Data`], e.g. to move it
+    /// elsewhere without cloning. See also [`Data::into_vector`] and [`Data::into_matrix`].
+    pub fn into_data(self) -> Data {
+        self.data
+    }
+
     /// Returns the inner types when ownership is required
     pub fn into_inner(self) -> (Data, Option<Stats>) {
         (self.data, self.stats)
     }
+
+    /// Compute a hash of the label sets and sample values contained in this result,
+    /// e.g. to detect if the result of a query changed between two consecutive polls.<br>
+    /// The hash does not depend on the order of the time series and is stable across
+    /// program runs and platforms. For vector and scalar results the sample timestamps
+    /// are not taken into account, as they equal the evaluation time of the query.
+    /// Query statistics are never taken into account.
+    pub fn content_hash(&self) -> u64 {
+        let mut series = match &self.data {
+            Data::Vector(v) => v
+                .iter()
+                .map(|s| {
+                    let mut hasher = Fnv1a::new();
+                    hasher.write_metric(&s.metric);
+                    hasher.write_f64(s.sample.value);
+                    hasher.finish()
+                })
+                .collect::<Vec<u64>>(),
+            Data::Matrix(m) => m
+                .iter()
+                .map(|s| {
+                    let mut hasher = Fnv1a::new();
+                    hasher.write_metric(&s.metric);
+                    for sample in &s.samples {
+                        hasher.write_f64(sample.timestamp);
+                        hasher.write_f64(sample.value);
+                    }
+                    hasher.finish()
+                })
+                .collect(),
+            Data::Scalar(s) => {
+                let mut hasher = Fnv1a::new();
+                hasher.write_f64(s.value);
+                vec![hasher.finish()]
+            }
+        };
+        series.sort_unstable();
+
+        let mut hasher = Fnv1a::new();
+        hasher.write(match self.data {
+            Data::Vector(_) => b"vector",
+            Data::Matrix(_) => b"matrix",
+            Data::Scalar(_) => b"scalar",
+        });
+        for hash in series {
+            hasher.write(&hash.to_le_bytes());
+        }
+        hasher.finish()
+    }
+
+    /// Collapse time series with identical label sets into one, e.g. when the
+    /// result was returned by a backend that queried multiple replicas without
+    /// deduplicating their data.<br>
+    /// For vector results the series carrying the most recent sample is kept.
+    /// For matrix results the series with the most samples is kept, or the one
+    /// with the most recent last sample if the sample counts are equal.
+    /// The order of the remaining series is preserved.
+    pub fn dedup_by_labels(&mut self) {
+        match &mut self.data {
+            Data::Vector(v) => dedup_by_metric(
+                v,
+                |s| &s.metric,
+                |kept, other| other.sample.timestamp > kept.sample.timestamp,
+            ),
+            Data::Matrix(m) => dedup_by_metric(
+                m,
+                |s| &s.metric,
+                |kept, other| {
+                    let last = |s: &RangeVector| s.samples.last().map(|s| s.timestamp);
+                    match other.samples.len().cmp(&kept.samples.len()) {
+                        std::cmp::Ordering::Greater => true,
+                        std::cmp::Ordering::Less => false,
+                        std::cmp::Ordering::Equal => last(other) > last(kept),
+                    }
+                },
+            ),
+            Data::Scalar(_) => {}
+        }
+    }
+
+    /// Write the result as newline-delimited JSON, e.g. to pipe it into `jq` or other tools
+    /// that process one JSON object per line.<br>
+    /// Vector results are written as one line per time series and matrix results as one line
+    /// per sample, each line holding the `metric` labels, the `timestamp` and the `value`.
+    /// A scalar result is written as a single line holding the `timestamp` and the `value`.
+    /// Values that are not finite (e.g. `NaN`) are written as `null`.
+    ///
+    /// ```text
+    /// {"metric":{"__name__":"up","job":"node"},"timestamp":1659794074.17,"value":1.0}
+    /// {"metric":{"__name__":"up","job":"prometheus"},"timestamp":1659794074.17,"value":1.0}
+    /// ```
+    pub fn write_ndjson<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        #[derive(Serialize)]
+        struct Line<'a> {
+            metric: &'a Metric,
+            timestamp: f64,
+            value: f64,
+        }
+
+        fn write_line<W: std::io::Write, T: Serialize>(w: &mut W, line: &T) -> std::io::Result<()> {
+            serde_json::to_writer(&mut *w, line)?;
+            w.write_all(b"\n")
+        }
+
+        match &self.data {
+            Data::Vector(v) => {
+                for s in v {
+                    write_line(
+                        w,
+                        &Line {
+                            metric: &s.metric,
+                            timestamp: s.sample.timestamp,
+                            value: s.sample.value,
+                        },
+                    )?;
+                }
+            }
+            Data::Matrix(m) => {
+                for s in m {
+                    for sample in &s.samples {
+                        write_line(
+                            w,
+                            &Line {
+                                metric: &s.metric,
+                                timestamp: sample.timestamp,
+                                value: sample.value,
+                            },
+                        )?;
+                    }
+                }
+            }
+            Data::Scalar(s) => write_line(w, s)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "table")]
+impl PromqlResult {
+    /// Render the result as a plain text table with aligned columns, e.g. to print
+    /// it to a terminal.<br>
+    /// Vector results contain one column per label and a column holding the sample
+    /// value of each series. Matrix results contain the label columns and the number
+    /// of samples of each series instead. A scalar result is rendered as a single value.
+    ///
+    /// This method is only available if the `table` feature is enabled.
+    ///
+    /// ```text
+    /// __name__  instance        job         value
+    /// up        localhost:9090  prometheus  1
+    /// up        localhost:9100  node        0
+    /// ```
+    pub fn to_table(&self) -> String {
+        fn label_rows<'a, I>(metrics: I) -> (Vec<String>, Vec<Vec<String>>)
+        where
+            I: Iterator<Item = &'a Metric> + Clone,
+        {
+            let names = metrics
+                .clone()
+                .flat_map(|m| m.labels.keys())
+                .collect::<std::collections::BTreeSet<&String>>();
+            let rows = metrics
+                .map(|m| {
+                    names
+                        .iter()
+                        .map(|n| m.get(n).unwrap_or_default().to_string())
+                        .collect()
+                })
+                .collect();
+            (names.into_iter().cloned().collect(), rows)
+        }
+
+        let (header, rows) = match &self.data {
+            Data::Vector(v) => {
+                let (mut header, mut rows) = label_rows(v.iter().map(|s| &s.metric));
+                header.push(String::from("value"));
+                for (row, s) in rows.iter_mut().zip(v) {
+                    row.push(s.sample.value.to_string());
+                }
+                (header, rows)
+            }
+            Data::Matrix(m) => {
+                let (mut header, mut rows) = label_rows(m.iter().map(|s| &s.metric));
+                header.push(String::from("samples"));
+                for (row, s) in rows.iter_mut().zip(m) {
+                    row.push(s.samples.len().to_string());
+                }
+                (header, rows)
+            }
+            Data::Scalar(s) => (vec![String::from("value")], vec![vec![s.value.to_string()]]),
+        };
+
+        let mut widths = header.iter().map(|h| h.len()).collect::<Vec<usize>>();
+        for row in &rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+
+        let mut table = String::new();
+        for row in std::iter::once(&header).chain(rows.iter()) {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, w)| format!("{:<width$}", cell, width = w))
+                .collect::<Vec<String>>()
+                .join("  ");
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+        table
+    }
+}
+
+// 64-bit FNV-1a hash. Unlike the hashers of the standard library its output is
+// guaranteed to be stable, so hashes may be persisted or compared across processes.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // Strings are prefixed by their length so that e.g. ("ab", "c") and ("a", "bc") differ.
+    fn write_str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+
+    fn write_f64(&mut self, value: f64) {
+        self.write(&value.to_bits().to_le_bytes());
+    }
+
+    fn write_metric(&mut self, metric: &Metric) {
+        self.write(&(metric.len() as u64).to_le_bytes());
+        for (name, value) in metric {
+            self.write_str(name);
+            self.write_str(value);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// Merge the matrices of consecutive time ranges into one matrix of continuous series.
+// Samples are appended in order, so a sample at a timestamp that was already covered
+// by the previous range (e.g. a shared boundary) is dropped.
+pub(crate) fn stitch_matrices(chunks: Vec<Vec<RangeVector>>) -> Vec<RangeVector> {
+    let mut stitched: Vec<RangeVector> = vec![];
+    let mut index: HashMap<Metric, usize> = HashMap::new();
+
+    for series in chunks.into_iter().flatten() {
+        match index.get(&series.metric) {
+            Some(&i) => {
+                let samples = &mut stitched[i].samples;
+                for sample in series.samples {
+                    if samples
+                        .last()
+                        .is_none_or(|l| sample.timestamp > l.timestamp)
+                    {
+                        samples.push(sample);
+                    }
+                }
+            }
+            None => {
+                index.insert(series.metric.clone(), stitched.len());
+                stitched.push(series);
+            }
+        }
+    }
+
+    stitched
+}
+
+// Remove all but one of the series that share the same label set. `prefer` decides
+// if the series that is currently kept should be replaced by a duplicate.
+fn dedup_by_metric<T>(
+    series: &mut Vec<T>,
+    metric: impl Fn(&T) -> &Metric,
+    prefer: impl Fn(&T, &T) -> bool,
+) {
+    let mut kept: Vec<T> = Vec::with_capacity(series.len());
+    let mut index: HashMap<Metric, usize> = HashMap::new();
+
+    for s in series.drain(..) {
+        match index.get(metric(&s)) {
+            Some(&i) => {
+                if prefer(&kept[i], &s) {
+                    kept[i] = s;
+                }
+            }
+            None => {
+                index.insert(metric(&s).clone(), kept.len());
+                kept.push(s);
+            }
+        }
+    }
+
+    *series = kept;
 }
 
 /// A wrapper for possible result types of expression queries ([`Client::query`](crate::Client::query) and [`Client::query_range`](crate::Client::query_range)).
+///
+/// Besides the borrowing accessors like [`Data::as_vector`] the consuming accessors
+/// [`Data::into_vector`], [`Data::into_matrix`] and [`Data::into_scalar`] return the owned
+/// result, or the unchanged [`Data`] as error if the result is of a different type.
 #[derive(Clone, Debug, Deserialize, Serialize, EnumAsInner)]
 #[serde(tag = "resultType", content = "result")]
 pub enum Data {
@@ -261,12 +595,145 @@ impl Data {
             Data::Scalar(_) => false,
         }
     }
+
+    /// Apply `f` to the value of every sample in place, e.g. to convert bytes to MiB.
+    /// Labels and timestamps are left unchanged. `f` is called for `NaN` and other
+    /// non-finite values as well.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::response::Data;
+    ///
+    /// fn bytes_to_mebibytes(data: &mut Data) {
+    ///     data.map_values(|v| v / (1024.0 * 1024.0));
+    /// }
+    /// ```
+    pub fn map_values(&mut self, f: impl Fn(f64) -> f64) {
+        match self {
+            Data::Vector(v) => v
+                .iter_mut()
+                .for_each(|s| s.sample.value = f(s.sample.value)),
+            Data::Matrix(m) => m
+                .iter_mut()
+                .flat_map(|s| s.samples.iter_mut())
+                .for_each(|s| s.value = f(s.value)),
+            Data::Scalar(s) => s.value = f(s.value),
+        }
+    }
+}
+
+/// The set of labels (+ metric name) that identifies a time series.
+///
+/// Labels are stored in lexicographical order of their names, so iterating over
+/// a [`Metric`] always yields the labels in the same order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Metric {
+    pub(crate) labels: BTreeMap<String, String>,
+}
+
+impl Metric {
+    /// Create an empty [`Metric`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the metric name, i.e. the value of the `__name__` label.
+    pub fn name(&self) -> Option<&str> {
+        self.get("__name__")
+    }
+
+    /// Returns the value of the given label.
+    pub fn get(&self, label: &str) -> Option<&str> {
+        self.labels.get(label).map(|v| v.as_str())
+    }
+
+    /// Check if the given label is part of this label set.
+    pub fn contains(&self, label: &str) -> bool {
+        self.labels.contains_key(label)
+    }
+
+    /// Insert a label, returning the previous value if the label was already present.
+    pub fn insert(&mut self, label: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.labels.insert(label.into(), value.into())
+    }
+
+    /// Remove a label, returning its value if the label was present.
+    pub fn remove(&mut self, label: &str) -> Option<String> {
+        self.labels.remove(label)
+    }
+
+    /// Returns the number of labels (including the metric name).
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Check if this label set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Returns an iterator over all label names and values sorted by label name.
+    pub fn iter(&self) -> btree_map::Iter<'_, String, String> {
+        self.labels.iter()
+    }
+
+    /// Returns the labels as a map sorted by label name, e.g. to derive a canonical key
+    /// of this label set.
+    pub fn as_btreemap(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    /// Returns the inner map when ownership is required
+    pub fn into_inner(self) -> BTreeMap<String, String> {
+        self.labels
+    }
+}
+
+impl From<BTreeMap<String, String>> for Metric {
+    fn from(labels: BTreeMap<String, String>) -> Self {
+        Metric { labels }
+    }
+}
+
+impl From<HashMap<String, String>> for Metric {
+    fn from(labels: HashMap<String, String>) -> Self {
+        labels.into_iter().collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Metric {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Metric {
+            labels: iter
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        }
+    }
+}
+
+impl IntoIterator for Metric {
+    type Item = (String, String);
+    type IntoIter = btree_map::IntoIter<String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.labels.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Metric {
+    type Item = (&'a String, &'a String);
+    type IntoIter = btree_map::Iter<'a, String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.labels.iter()
+    }
 }
 
 /// A single time series containing a single data point/sample.
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct InstantVector {
-    pub(crate) metric: HashMap<String, String>,
+    pub(crate) metric: Metric,
     #[serde(alias = "value")]
     pub(crate) sample: Sample,
 }
@@ -274,7 +741,7 @@ pub struct InstantVector {
 impl InstantVector {
     /// Returns a reference to the set of labels (+ metric name)
     /// of this time series.
-    pub fn metric(&self) -> &HashMap<String, String> {
+    pub fn metric(&self) -> &Metric {
         &self.metric
     }
 
@@ -283,9 +750,15 @@ impl InstantVector {
         &self.sample
     }
 
+    /// Returns the timestamp of the sample of this time series.
+    /// This is a shorthand for `self.sample().timestamp()`.
+    pub fn timestamp(&self) -> f64 {
+        self.sample.timestamp
+    }
+
     /// Returns a mutable reference to the set of labels (+ metric name)
     /// of this time series.
-    pub fn metric_mut(&mut self) -> &mut HashMap<String, String> {
+    pub fn metric_mut(&mut self) -> &mut Metric {
         &mut self.metric
     }
 
@@ -295,15 +768,179 @@ impl InstantVector {
     }
 
     /// Returns the inner types when ownership is required
-    pub fn into_inner(self) -> (HashMap<String, String>, Sample) {
+    pub fn into_inner(self) -> (Metric, Sample) {
         (self.metric, self.sample)
     }
+
+    /// Returns a copy of this time series whose label set only contains the given labels.
+    /// Note that the metric name (`__name__`) is removed as well unless it is listed explicitly.
+    pub fn project_labels(&self, labels: &[&str]) -> Self {
+        let metric = self
+            .metric
+            .iter()
+            .filter(|(k, _)| labels.contains(&k.as_str()))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        InstantVector {
+            metric,
+            sample: self.sample,
+        }
+    }
+
+    /// Returns a copy of this time series whose label set does not contain the given labels,
+    /// similar to the PromQL `without` clause. The metric name may be removed by listing `__name__`.
+    pub fn drop_labels(&self, labels: &[&str]) -> Self {
+        let metric = self
+            .metric
+            .iter()
+            .filter(|(k, _)| !labels.contains(&k.as_str()))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        InstantVector {
+            metric,
+            sample: self.sample,
+        }
+    }
+}
+
+/// Group the given series by the values of the given labels, similar to the PromQL `by` clause.
+/// The key of each group holds the label values in the order of `labels`, using an empty string
+/// for labels that a series does not have. Within a group the series keep their original order.
+///
+/// ```rust
+/// use prometheus_http_query::response::{group_by, InstantVector};
+///
+/// // Count the number of series per job.
+/// fn series_per_job(vector: &[InstantVector]) -> Vec<(String, usize)> {
+///     group_by(vector, &["job"])
+///         .into_iter()
+///         .map(|(mut key, series)| (key.remove(0), series.len()))
+///         .collect()
+/// }
+/// ```
+pub fn group_by<'a>(
+    series: &'a [InstantVector],
+    labels: &[&str],
+) -> HashMap<Vec<String>, Vec<&'a InstantVector>> {
+    let mut groups: HashMap<Vec<String>, Vec<&'a InstantVector>> = HashMap::new();
+    for s in series {
+        let key = labels
+            .iter()
+            .map(|l| s.metric.get(l).unwrap_or_default().to_string())
+            .collect();
+        groups.entry(key).or_default().push(s);
+    }
+    groups
+}
+
+/// The differences between two instant vectors as computed by [`diff_vectors`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VectorDiff<'a> {
+    added: Vec<&'a InstantVector>,
+    removed: Vec<&'a InstantVector>,
+    changed: Vec<(&'a InstantVector, &'a InstantVector)>,
+}
+
+impl<'a> VectorDiff<'a> {
+    /// Returns the series whose label sets only appear in the new vector.
+    pub fn added(&self) -> &[&'a InstantVector] {
+        &self.added
+    }
+
+    /// Returns the series whose label sets only appear in the old vector.
+    pub fn removed(&self) -> &[&'a InstantVector] {
+        &self.removed
+    }
+
+    /// Returns pairs of the old and the new series that share the same label set
+    /// but have a different sample value.
+    pub fn changed(&self) -> &[(&'a InstantVector, &'a InstantVector)] {
+        &self.changed
+    }
+
+    /// Check if the two vectors contain the same series with the same sample values.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two instant vectors, e.g. the results of the same query taken at different times,
+/// and report which series were added, removed or changed their sample value. Series are
+/// identified by their label set, timestamps are ignored.<br>
+/// Added and changed series are reported in the order of `new`, removed series in the order of `old`.
+///
+/// ```rust
+/// use prometheus_http_query::response::{diff_vectors, InstantVector};
+///
+/// // Report the targets that went down since the last check.
+/// fn down_since(old: &[InstantVector], new: &[InstantVector]) -> Vec<String> {
+///     diff_vectors(old, new)
+///         .changed()
+///         .iter()
+///         .filter(|(_, new)| new.sample().value() == 0.0)
+///         .filter_map(|(_, new)| new.metric().get("instance").map(String::from))
+///         .collect()
+/// }
+/// ```
+pub fn diff_vectors<'a>(old: &'a [InstantVector], new: &'a [InstantVector]) -> VectorDiff<'a> {
+    let old_index: HashMap<&Metric, &InstantVector> = old.iter().map(|s| (&s.metric, s)).collect();
+    let new_index: HashMap<&Metric, &InstantVector> = new.iter().map(|s| (&s.metric, s)).collect();
+
+    let mut diff = VectorDiff::default();
+    for s in new {
+        match old_index.get(&s.metric) {
+            None => diff.added.push(s),
+            Some(o) => {
+                let (a, b) = (o.sample.value, s.sample.value);
+                if a != b && !(a.is_nan() && b.is_nan()) {
+                    diff.changed.push((o, s));
+                }
+            }
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|s| !new_index.contains_key(&s.metric))
+        .collect();
+    diff
+}
+
+/// Return the expected label sets that none of the given series carries, e.g. to find out
+/// which of the known instances of a job did not report a metric. Unlike the PromQL
+/// `absent()` function this reports every missing label set individually.<br>
+/// An expected label set is present if a series has all of its labels with the same values,
+/// additional labels of the series are ignored. The missing label sets keep their original order.
+///
+/// ```rust
+/// use prometheus_http_query::response::{missing_series, InstantVector, Metric};
+///
+/// // List the instances that are expected to be up but did not report the metric.
+/// fn missing_instances(vector: &[InstantVector], instances: &[&str]) -> Vec<String> {
+///     let expected: Vec<Metric> = instances
+///         .iter()
+///         .map(|i| [("instance".to_string(), i.to_string())].into_iter().collect())
+///         .collect();
+///     missing_series(vector, &expected)
+///         .into_iter()
+///         .map(|m| m.get("instance").unwrap().to_string())
+///         .collect()
+/// }
+/// ```
+pub fn missing_series<'a>(series: &[InstantVector], expected: &'a [Metric]) -> Vec<&'a Metric> {
+    expected
+        .iter()
+        .filter(|e| {
+            !series
+                .iter()
+                .any(|s| e.iter().all(|(k, v)| s.metric.get(k) == Some(v.as_str())))
+        })
+        .collect()
 }
 
 /// A single time series containing a range of data points/samples.
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct RangeVector {
-    pub(crate) metric: HashMap<String, String>,
+    pub(crate) metric: Metric,
     #[serde(alias = "values")]
     pub(crate) samples: Vec<Sample>,
 }
@@ -311,7 +948,7 @@ pub struct RangeVector {
 impl RangeVector {
     /// Returns a reference to the set of labels (+ metric name)
     /// of this time series.
-    pub fn metric(&self) -> &HashMap<String, String> {
+    pub fn metric(&self) -> &Metric {
         &self.metric
     }
 
@@ -321,9 +958,70 @@ impl RangeVector {
     }
 
     /// Returns the inner types when ownership is required
-    pub fn into_inner(self) -> (HashMap<String, String>, Vec<Sample>) {
+    pub fn into_inner(self) -> (Metric, Vec<Sample>) {
         (self.metric, self.samples)
     }
+
+    /// Returns the timestamps of all samples whose value is lower than the value of
+    /// the sample preceding it, i.e. the points where a counter was reset.<br>
+    /// Samples with a `NaN` value are skipped, so a value is always compared
+    /// against the last valid value before it.
+    /// Timestamps are returned in milliseconds since the Unix epoch, see [`Sample::unix_millis`].
+    pub fn reset_points(&self) -> Vec<i64> {
+        let mut previous: Option<f64> = None;
+        let mut resets = vec![];
+        for sample in self.samples.iter().filter(|s| !s.value.is_nan()) {
+            if previous.is_some_and(|p| sample.value < p) {
+                resets.push(sample.unix_millis());
+            }
+            previous = Some(sample.value);
+        }
+        resets
+    }
+
+    /// Compute the per-second rate of increase between each pair of consecutive samples of
+    /// this counter series, e.g. to analyze exported data offline without querying it again.<br>
+    /// Each sample of the returned series is placed at the timestamp of the later of the two
+    /// samples. A value that is lower than the value before it is treated as a counter reset,
+    /// i.e. the counter is assumed to have restarted from zero. Samples with a `NaN` value are
+    /// skipped. Like the PromQL `rate()` function the returned series lacks the metric name.
+    ///
+    /// Note that this is only an approximation of the server-side `rate()` function, which
+    /// computes the average rate over a window and extrapolates towards the window boundaries.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::response::RangeVector;
+    ///
+    /// fn requests_per_second(series: &RangeVector) -> Vec<f64> {
+    ///     series.to_rate().samples().iter().map(|s| s.value()).collect()
+    /// }
+    /// ```
+    pub fn to_rate(&self) -> RangeVector {
+        let mut metric = self.metric.clone();
+        metric.remove("__name__");
+
+        let mut previous: Option<&Sample> = None;
+        let mut samples = vec![];
+        for sample in self.samples.iter().filter(|s| !s.value.is_nan()) {
+            if let Some(p) = previous {
+                let elapsed = sample.timestamp - p.timestamp;
+                if elapsed <= 0.0 {
+                    continue;
+                }
+                let increase = match sample.value < p.value {
+                    true => sample.value,
+                    false => sample.value - p.value,
+                };
+                samples.push(Sample {
+                    timestamp: sample.timestamp,
+                    value: increase / elapsed,
+                });
+            }
+            previous = Some(sample);
+        }
+
+        RangeVector { metric, samples }
+    }
 }
 
 /// A single data point.
@@ -340,11 +1038,48 @@ impl Sample {
         self.timestamp
     }
 
+    /// Returns the timestamp contained in this sample as [`Timestamp`].
+    pub fn as_timestamp(&self) -> Timestamp {
+        Timestamp::from_secs_f64(self.timestamp)
+    }
+
+    /// Returns the timestamp contained in this sample in milliseconds since the Unix epoch.
+    /// This is a shorthand for `self.as_timestamp().unix_millis()`.
+    pub fn unix_millis(&self) -> i64 {
+        self.as_timestamp().unix_millis()
+    }
+
     /// Returns the value contained in this sample.
     pub fn value(&self) -> f64 {
         self.value
     }
 
+    /// Interpret the value of this sample as a Unix timestamp (seconds), e.g. when it was
+    /// returned by the PromQL functions `timestamp()` or `time()`, and return it as a date-time
+    /// with the given UTC offset.<br>
+    /// This is useful to convert timestamps to a local time zone, as PromQL functions like
+    /// `hour()` or `day_of_week()` are always evaluated in UTC.
+    /// Returns `None` if the value is not finite or out of the supported range.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::response::Sample;
+    /// use time::{macros::{datetime, offset}, UtcOffset};
+    ///
+    /// let sample: Sample = serde_json::from_str(r#"[1700000000, "1699999200"]"#).unwrap();
+    ///
+    /// assert_eq!(sample.value_as_datetime(UtcOffset::UTC), Some(datetime!(2023-11-14 22:00:00 UTC)));
+    /// assert_eq!(sample.value_as_datetime(offset!(+2)), Some(datetime!(2023-11-15 00:00:00 +2)));
+    /// ```
+    pub fn value_as_datetime(&self, offset: UtcOffset) -> Option<OffsetDateTime> {
+        if !self.value.is_finite() {
+            return None;
+        }
+        let nanos = (self.value * 1e9).round() as i128;
+        OffsetDateTime::from_unix_timestamp_nanos(nanos)
+            .ok()
+            .and_then(|t| t.checked_to_offset(offset))
+    }
+
     /// Returns a mutable reference to the timestamp contained in this sample.
     pub fn timestamp_mut(&mut self) -> &mut f64 {
         &mut self.timestamp
@@ -441,6 +1176,12 @@ impl ActiveTarget {
         &self.last_scrape
     }
 
+    /// Get the time when the last scrape occurred as [`Timestamp`], e.g. to obtain it
+    /// in milliseconds like the timestamps of samples.
+    pub fn last_scrape_timestamp(&self) -> Timestamp {
+        Timestamp::from(self.last_scrape)
+    }
+
     /// Get the duration that the last scrape ran for in seconds.
     pub fn last_scrape_duration(&self) -> f64 {
         self.last_scrape_duration
@@ -692,7 +1433,7 @@ pub struct Alert {
     #[serde(with = "time::serde::rfc3339")]
     pub(crate) active_at: OffsetDateTime,
     pub(crate) annotations: HashMap<String, String>,
-    pub(crate) labels: HashMap<String, String>,
+    pub(crate) labels: Metric,
     pub(crate) state: AlertState,
     #[serde(deserialize_with = "de::deserialize_f64")]
     pub(crate) value: f64,
@@ -704,13 +1445,19 @@ impl Alert {
         &self.active_at
     }
 
+    /// Get the time when this alert started firing as [`Timestamp`], e.g. to obtain it
+    /// in milliseconds like the timestamps of samples.
+    pub fn active_at_timestamp(&self) -> Timestamp {
+        Timestamp::from(self.active_at)
+    }
+
     /// Get a set of annotations associated with this alert.
     pub fn annotations(&self) -> &HashMap<String, String> {
         &self.annotations
     }
 
-    /// Get a set of labels associated with this alert.
-    pub fn labels(&self) -> &HashMap<String, String> {
+    /// Get the set of labels associated with this alert.
+    pub fn labels(&self) -> &Metric {
         &self.labels
     }
 
@@ -759,6 +1506,33 @@ impl Alertmanager {
     }
 }
 
+/// A notification about the operational state of the Prometheus server, e.g.
+/// that a configuration reload failed or that the server is shutting down.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Notification {
+    text: String,
+    #[serde(with = "time::serde::rfc3339")]
+    date: OffsetDateTime,
+    active: bool,
+}
+
+impl Notification {
+    /// Get the text of this notification.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the time at which this notification was last updated.
+    pub fn date(&self) -> &OffsetDateTime {
+        &self.date
+    }
+
+    /// Check if this notification is still active.
+    pub fn active(&self) -> bool {
+        self.active
+    }
+}
+
 /// Possible metric types that the HTTP API may return.
 #[derive(Debug, Copy, Clone, Deserialize, Eq, PartialEq)]
 pub enum MetricType {
@@ -1061,6 +1835,8 @@ impl TsdbStatistics {
 pub struct HeadStatistics {
     #[serde(alias = "numSeries")]
     pub(crate) num_series: usize,
+    #[serde(alias = "numLabelPairs", default)]
+    pub(crate) num_label_pairs: Option<usize>,
     #[serde(alias = "chunkCount")]
     pub(crate) chunk_count: usize,
     #[serde(alias = "minTime")]
@@ -1075,6 +1851,12 @@ impl HeadStatistics {
         self.num_series
     }
 
+    /// Get the number of label name/value pairs. Returns `None` if the server is too old
+    /// to report this number.
+    pub fn num_label_pairs(&self) -> Option<usize> {
+        self.num_label_pairs
+    }
+
     /// Get the number of chunks.
     pub fn chunk_count(&self) -> usize {
         self.chunk_count
@@ -1089,6 +1871,31 @@ impl HeadStatistics {
     pub fn max_time(&self) -> i64 {
         self.max_time
     }
+
+    /// Get the current minimum timestamp as UTC date-time. Returns `None` if the head block
+    /// is empty, in which case Prometheus reports a placeholder value.
+    pub fn min_time_as_datetime(&self) -> Option<OffsetDateTime> {
+        self.time_span().and(millis_to_datetime(self.min_time))
+    }
+
+    /// Get the current maximum timestamp as UTC date-time. Returns `None` if the head block
+    /// is empty, in which case Prometheus reports a placeholder value.
+    pub fn max_time_as_datetime(&self) -> Option<OffsetDateTime> {
+        self.time_span().and(millis_to_datetime(self.max_time))
+    }
+
+    /// Get the time span covered by the head block, i.e. the difference between the
+    /// maximum and the minimum timestamp. Returns `None` if the head block is empty.
+    pub fn time_span(&self) -> Option<Duration> {
+        match self.max_time.checked_sub(self.min_time) {
+            Some(span) if span >= 0 => Some(Duration::milliseconds(span)),
+            _ => None,
+        }
+    }
+}
+
+fn millis_to_datetime(millis: i64) -> Option<OffsetDateTime> {
+    OffsetDateTime::from_unix_timestamp_nanos(i128::from(millis) * 1_000_000).ok()
 }
 
 /// Prometheus TSDB item counts used in different contexts (e.g. series count, label value count ...).
@@ -1204,7 +2011,7 @@ mod tests {
 "#;
 
         let result = serde_json::from_str::<ApiResponse<PromqlResult>>(data)?;
-        assert!(matches!(result, ApiResponse::Success { data: _ }));
+        assert!(matches!(result, ApiResponse::Success { data: _, .. }));
 
         Ok(())
     }
@@ -1456,6 +2263,638 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_dedup_vector_by_labels() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "resultType": "vector",
+  "result": [
+    { "metric": { "__name__": "up", "job": "node" }, "value": [ 1435781451.781, "1" ] },
+    { "metric": { "__name__": "up", "job": "prometheus" }, "value": [ 1435781451.781, "1" ] },
+    { "metric": { "job": "node", "__name__": "up" }, "value": [ 1435781460.781, "0" ] }
+  ]
+}
+"#;
+        let mut result = serde_json::from_str::<PromqlResult>(data)?;
+        result.dedup_by_labels();
+        let vector = result.data().as_vector().unwrap();
+        assert!(vector.len() == 2);
+        assert!(vector[0].metric().get("job") == Some("node"));
+        assert!(vector[0].sample().timestamp() == 1435781460.781);
+        assert!(vector[0].sample().value() == 0.0);
+        assert!(vector[1].metric().get("job") == Some("prometheus"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup_matrix_by_labels() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "resultType": "matrix",
+  "result": [
+    {
+      "metric": { "__name__": "up", "replica": "a" },
+      "values": [ [ 1435781430, "1" ], [ 1435781445, "1" ] ]
+    },
+    {
+      "metric": { "__name__": "up", "job": "node" },
+      "values": [ [ 1435781430, "1" ] ]
+    },
+    {
+      "metric": { "__name__": "up", "job": "node" },
+      "values": [ [ 1435781430, "1" ], [ 1435781445, "1" ], [ 1435781460, "1" ] ]
+    },
+    {
+      "metric": { "__name__": "up", "replica": "a" },
+      "values": [ [ 1435781445, "1" ], [ 1435781460, "1" ] ]
+    },
+    {
+      "metric": { "__name__": "up", "job": "node" },
+      "values": [ [ 1435781400, "1" ], [ 1435781415, "1" ] ]
+    }
+  ]
+}
+"#;
+        let mut result = serde_json::from_str::<PromqlResult>(data)?;
+        result.dedup_by_labels();
+        let matrix = result.data().as_matrix().unwrap();
+        assert!(matrix.len() == 2);
+        // Equal sample count, the series with the most recent sample wins.
+        assert!(matrix[0].metric().get("replica") == Some("a"));
+        assert!(matrix[0].samples().len() == 2);
+        assert!(matrix[0].samples()[1].timestamp() == 1435781460.0);
+        // The series with the most samples wins.
+        assert!(matrix[1].metric().get("job") == Some("node"));
+        assert!(matrix[1].samples().len() == 3);
+        Ok(())
+    }
+
+    #[cfg(feature = "table")]
+    #[test]
+    fn test_vector_to_table() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "resultType": "vector",
+  "result": [
+    { "metric": { "__name__": "up", "job": "prometheus", "instance": "localhost:9090" }, "value": [ 1435781451.781, "1" ] },
+    { "metric": { "__name__": "up", "job": "node", "instance": "localhost:9100" }, "value": [ 1435781451.781, "0" ] },
+    { "metric": { "__name__": "up", "instance": "localhost:9091" }, "value": [ 1435781451.781, "0.5" ] }
+  ]
+}
+"#;
+        let result = serde_json::from_str::<PromqlResult>(data)?;
+        let expected = "\
+__name__  instance        job         value
+up        localhost:9090  prometheus  1
+up        localhost:9100  node        0
+up        localhost:9091              0.5
+";
+        assert_eq!(result.to_table(), expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "table")]
+    #[test]
+    fn test_matrix_to_table() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "resultType": "matrix",
+  "result": [
+    { "metric": { "__name__": "up", "job": "node" }, "values": [ [ 1435781430, "1" ], [ 1435781445, "1" ] ] }
+  ]
+}
+"#;
+        let result = serde_json::from_str::<PromqlResult>(data)?;
+        assert_eq!(
+            result.to_table(),
+            "__name__  job   samples\nup        node  2\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_vector_reset_points() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "metric": { "__name__": "http_requests_total", "job": "api" },
+  "values": [
+    [ 1435781430, "10" ],
+    [ 1435781445, "15" ],
+    [ 1435781460, "NaN" ],
+    [ 1435781475, "3" ],
+    [ 1435781490, "3" ],
+    [ 1435781505, "8" ]
+  ]
+}
+"#;
+        let series = serde_json::from_str::<RangeVector>(data)?;
+        assert_eq!(series.reset_points(), vec![1435781475000]);
+
+        let data = r#"
+{
+  "metric": { "__name__": "http_requests_total", "job": "api" },
+  "values": [
+    [ 1435781430, "1" ],
+    [ 1435781445, "1" ],
+    [ 1435781460, "NaN" ],
+    [ 1435781475, "2" ],
+    [ 1435781490, "5" ]
+  ]
+}
+"#;
+        let series = serde_json::from_str::<RangeVector>(data)?;
+        assert!(series.reset_points().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_vector_to_rate() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "metric": { "__name__": "http_requests_total", "job": "api" },
+  "values": [
+    [ 1435781430, "10" ],
+    [ 1435781445, "40" ],
+    [ 1435781460, "NaN" ],
+    [ 1435781475, "15" ],
+    [ 1435781490, "15" ],
+    [ 1435781505, "30" ]
+  ]
+}
+"#;
+        let series = serde_json::from_str::<RangeVector>(data)?;
+        let rate = series.to_rate();
+        assert_eq!(rate.metric().name(), None);
+        assert_eq!(rate.metric().get("job"), Some("api"));
+        let samples: Vec<(f64, f64)> = rate
+            .samples()
+            .iter()
+            .map(|s| (s.timestamp(), s.value()))
+            .collect();
+        assert_eq!(
+            samples,
+            vec![
+                (1435781445.0, 2.0),
+                // The counter was reset and increased by 15 within 30 seconds.
+                (1435781475.0, 0.5),
+                (1435781490.0, 0.0),
+                (1435781505.0, 1.0),
+            ]
+        );
+
+        let data = r#"{ "metric": { "job": "api" }, "values": [ [ 1435781430, "10" ] ] }"#;
+        let series = serde_json::from_str::<RangeVector>(data)?;
+        assert!(series.to_rate().samples().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_instant_vector_label_projection() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "metric": { "__name__": "up", "job": "node", "instance": "localhost:9100", "env": "prod" },
+  "value": [ 1435781451.781, "1" ]
+}
+"#;
+        let series = serde_json::from_str::<InstantVector>(data)?;
+
+        let projected = series.project_labels(&["job", "instance", "missing"]);
+        assert_eq!(
+            projected.metric(),
+            &Metric::from_iter([("instance", "localhost:9100"), ("job", "node")])
+        );
+        assert_eq!(projected.metric().name(), None);
+        assert_eq!(projected.sample(), series.sample());
+
+        let projected = series.project_labels(&["__name__", "job"]);
+        assert_eq!(projected.metric().name(), Some("up"));
+        assert_eq!(projected.metric().len(), 2);
+
+        assert!(series.project_labels(&[]).metric().is_empty());
+
+        let dropped = series.drop_labels(&["instance", "env"]);
+        assert_eq!(
+            dropped.metric(),
+            &Metric::from_iter([("__name__", "up"), ("job", "node")])
+        );
+        assert_eq!(dropped.sample(), series.sample());
+
+        let dropped = series.drop_labels(&["__name__"]);
+        assert_eq!(dropped.metric().name(), None);
+        assert_eq!(dropped.metric().len(), 3);
+
+        // The original series is left untouched.
+        assert_eq!(series.metric().len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_metric_canonical_order() -> Result<(), anyhow::Error> {
+        let data = [
+            r#"{ "job": "node", "__name__": "up", "instance": "localhost:9100", "env": "prod" }"#,
+            r#"{ "instance": "localhost:9100", "env": "prod", "job": "node", "__name__": "up" }"#,
+        ];
+        let expected = ["__name__", "env", "instance", "job"];
+        for data in data {
+            let metric = serde_json::from_str::<Metric>(data)?;
+            let names: Vec<&str> = metric.as_btreemap().keys().map(String::as_str).collect();
+            assert_eq!(names, expected);
+            assert_eq!(
+                serde_json::to_string(&metric)?,
+                r#"{"__name__":"up","env":"prod","instance":"localhost:9100","job":"node"}"#
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by() -> Result<(), anyhow::Error> {
+        let data = r#"
+[
+  { "metric": { "job": "node", "instance": "a", "mode": "idle" }, "value": [ 1435781451.781, "1" ] },
+  { "metric": { "job": "api", "instance": "b", "mode": "idle" }, "value": [ 1435781451.781, "2" ] },
+  { "metric": { "job": "node", "instance": "c", "mode": "user" }, "value": [ 1435781451.781, "3" ] },
+  { "metric": { "job": "node", "instance": "d", "mode": "idle" }, "value": [ 1435781451.781, "4" ] },
+  { "metric": { "instance": "e" }, "value": [ 1435781451.781, "5" ] }
+]
+"#;
+        let vector = serde_json::from_str::<Vec<InstantVector>>(data)?;
+        let key = |k: &[&str]| k.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let values =
+            |g: &[&InstantVector]| g.iter().map(|s| s.sample().value()).collect::<Vec<_>>();
+
+        let groups = group_by(&vector, &["job"]);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(values(&groups[&key(&["node"])]), vec![1.0, 3.0, 4.0]);
+        assert_eq!(values(&groups[&key(&["api"])]), vec![2.0]);
+        assert_eq!(values(&groups[&key(&[""])]), vec![5.0]);
+
+        let groups = group_by(&vector, &["job", "mode"]);
+        assert_eq!(groups.len(), 4);
+        assert_eq!(values(&groups[&key(&["node", "idle"])]), vec![1.0, 4.0]);
+        assert_eq!(values(&groups[&key(&["node", "user"])]), vec![3.0]);
+        assert_eq!(values(&groups[&key(&["api", "idle"])]), vec![2.0]);
+        assert_eq!(values(&groups[&key(&["", ""])]), vec![5.0]);
+
+        let groups = group_by(&vector, &[]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[&Vec::new()].len(), 5);
+
+        assert!(group_by(&[], &["job"]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_series() -> Result<(), anyhow::Error> {
+        let data = r#"
+[
+  { "metric": { "__name__": "up", "job": "node", "instance": "a" }, "value": [ 1435781451.781, "1" ] },
+  { "metric": { "__name__": "up", "job": "node", "instance": "c" }, "value": [ 1435781451.781, "0" ] },
+  { "metric": { "__name__": "up", "job": "api", "instance": "b" }, "value": [ 1435781451.781, "1" ] }
+]
+"#;
+        let vector = serde_json::from_str::<Vec<InstantVector>>(data)?;
+        let metric = |labels: &[(&str, &str)]| -> Metric {
+            labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let expected = vec![
+            metric(&[("job", "node"), ("instance", "a")]),
+            metric(&[("job", "node"), ("instance", "b")]),
+            metric(&[("instance", "c")]),
+            metric(&[("job", "api"), ("instance", "d")]),
+        ];
+        let missing = missing_series(&vector, &expected);
+        assert_eq!(missing, vec![&expected[1], &expected[3]]);
+
+        // All expected series are present.
+        let expected = vec![metric(&[("instance", "b")]), metric(&[("job", "node")])];
+        assert!(missing_series(&vector, &expected).is_empty());
+
+        // Without any series every expected label set is missing.
+        assert_eq!(missing_series(&[], &expected).len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_vectors() -> Result<(), anyhow::Error> {
+        let old = r#"
+[
+  { "metric": { "job": "node", "instance": "a" }, "value": [ 1435781451.781, "1" ] },
+  { "metric": { "job": "node", "instance": "b" }, "value": [ 1435781451.781, "1" ] },
+  { "metric": { "job": "node", "instance": "c" }, "value": [ 1435781451.781, "1" ] },
+  { "metric": { "job": "node", "instance": "d" }, "value": [ 1435781451.781, "NaN" ] }
+]
+"#;
+        let new = r#"
+[
+  { "metric": { "job": "node", "instance": "e" }, "value": [ 1435781511.781, "1" ] },
+  { "metric": { "job": "node", "instance": "c" }, "value": [ 1435781511.781, "0" ] },
+  { "metric": { "job": "node", "instance": "a" }, "value": [ 1435781511.781, "1" ] },
+  { "metric": { "job": "node", "instance": "d" }, "value": [ 1435781511.781, "NaN" ] }
+]
+"#;
+        let old = serde_json::from_str::<Vec<InstantVector>>(old)?;
+        let new = serde_json::from_str::<Vec<InstantVector>>(new)?;
+        let instance = |s: &InstantVector| s.metric().get("instance").unwrap().to_string();
+
+        let diff = diff_vectors(&old, &new);
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.added().iter().map(|s| instance(s)).collect::<Vec<_>>(),
+            vec!["e"]
+        );
+        assert_eq!(
+            diff.removed()
+                .iter()
+                .map(|s| instance(s))
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+        assert_eq!(diff.changed().len(), 1);
+        let (before, after) = diff.changed()[0];
+        assert_eq!(instance(before), "c");
+        assert_eq!(before.sample().value(), 1.0);
+        assert_eq!(after.sample().value(), 0.0);
+
+        // Only the timestamps differ.
+        assert!(diff_vectors(&old[..1], &new[2..3]).is_empty());
+        assert!(diff_vectors(&old, &old).is_empty());
+
+        let diff = diff_vectors(&[], &new);
+        assert_eq!(diff.added().len(), 4);
+        assert!(diff.removed().is_empty());
+        let diff = diff_vectors(&old, &[]);
+        assert_eq!(diff.removed().len(), 4);
+        assert!(diff.added().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_map_values() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "resultType": "vector",
+  "result": [
+    { "metric": { "job": "a" }, "value": [ 1435781451.781, "2048" ] },
+    { "metric": { "job": "b" }, "value": [ 1435781451.781, "NaN" ] }
+  ]
+}
+"#;
+        let mut data = serde_json::from_str::<Data>(data)?;
+        data.map_values(|v| v / 1024.0);
+        let vector = data.as_vector().unwrap();
+        assert_eq!(vector[0].metric().get("job"), Some("a"));
+        assert_eq!(vector[0].sample().timestamp(), 1435781451.781);
+        assert_eq!(vector[0].sample().value(), 2.0);
+        assert!(vector[1].sample().value().is_nan());
+
+        // NaN handling is left to the closure.
+        data.map_values(|v| if v.is_nan() { 0.0 } else { v });
+        assert_eq!(data.as_vector().unwrap()[1].sample().value(), 0.0);
+
+        let data = r#"
+{
+  "resultType": "matrix",
+  "result": [
+    { "metric": { "job": "a" }, "values": [ [ 1435781430, "1.5" ], [ 1435781445, "2" ] ] },
+    { "metric": { "job": "b" }, "values": [ [ 1435781430, "3" ] ] }
+  ]
+}
+"#;
+        let mut data = serde_json::from_str::<Data>(data)?;
+        data.map_values(|v| v * 1000.0);
+        let matrix = data.as_matrix().unwrap();
+        let samples: Vec<(f64, f64)> = matrix
+            .iter()
+            .flat_map(|s| s.samples())
+            .map(|s| (s.timestamp(), s.value()))
+            .collect();
+        assert_eq!(
+            samples,
+            vec![
+                (1435781430.0, 1500.0),
+                (1435781445.0, 2000.0),
+                (1435781430.0, 3000.0)
+            ]
+        );
+
+        let mut data = serde_json::from_str::<Data>(
+            r#"{ "resultType": "scalar", "result": [ 1435781451.781, "4" ] }"#,
+        )?;
+        data.map_values(f64::sqrt);
+        assert_eq!(data.as_scalar().unwrap().value(), 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_data() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "resultType": "vector",
+  "result": [
+    { "metric": { "__name__": "up", "job": "node" }, "value": [ 1435781451.781, "1" ] }
+  ]
+}
+"#;
+        let result = serde_json::from_str::<PromqlResult>(data)?;
+        let vector = result.into_data().into_vector().unwrap();
+        assert_eq!(vector.len(), 1);
+        let (metric, sample) = vector.into_iter().next().unwrap().into_inner();
+        assert_eq!(metric.get("job"), Some("node"));
+        assert_eq!(sample.value(), 1.0);
+
+        let data = r#"
+{
+  "resultType": "matrix",
+  "result": [
+    { "metric": { "__name__": "up" }, "values": [ [ 1435781430.781, "1" ], [ 1435781445.781, "0" ] ] }
+  ]
+}
+"#;
+        let data = serde_json::from_str::<PromqlResult>(data)?.into_data();
+        // A mismatching accessor hands back the data unchanged.
+        let data = data.into_vector().unwrap_err();
+        let matrix = data.into_matrix().unwrap();
+        assert_eq!(matrix[0].samples().len(), 2);
+
+        let data = r#"{ "resultType": "scalar", "result": [ 1435781451.781, "42" ] }"#;
+        let data = serde_json::from_str::<PromqlResult>(data)?.into_data();
+        assert_eq!(data.into_scalar().unwrap().value(), 42.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_ndjson() -> Result<(), anyhow::Error> {
+        let ndjson = |data: &str| -> Result<Vec<serde_json::Value>, anyhow::Error> {
+            let result = serde_json::from_str::<PromqlResult>(data)?;
+            let mut buffer = vec![];
+            result.write_ndjson(&mut buffer)?;
+            let output = String::from_utf8(buffer)?;
+            assert!(output.ends_with('\n'));
+            Ok(output
+                .lines()
+                .map(serde_json::from_str)
+                .collect::<Result<_, _>>()?)
+        };
+
+        let vector = r#"
+{
+  "resultType": "vector",
+  "result": [
+    { "metric": { "job": "a" }, "value": [ 1659794074.17, "1" ] },
+    { "metric": { "job": "b" }, "value": [ 1659794074.17, "NaN" ] }
+  ]
+}
+"#;
+        let lines = ndjson(vector)?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["metric"]["job"], "a");
+        assert_eq!(lines[0]["timestamp"], 1659794074.17);
+        assert_eq!(lines[0]["value"], 1.0);
+        assert!(lines[1]["value"].is_null());
+
+        let matrix = r#"
+{
+  "resultType": "matrix",
+  "result": [
+    { "metric": { "job": "a" }, "values": [ [ 0, "1" ], [ 30, "2" ], [ 60, "3" ] ] },
+    { "metric": { "job": "b" }, "values": [ [ 30, "4" ] ] },
+    { "metric": { "job": "c" }, "values": [] }
+  ]
+}
+"#;
+        let lines = ndjson(matrix)?;
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2]["metric"]["job"], "a");
+        assert_eq!(lines[2]["timestamp"], 60.0);
+        assert_eq!(lines[3]["metric"]["job"], "b");
+        assert_eq!(lines[3]["value"], 4.0);
+
+        let scalar = r#"{ "resultType": "scalar", "result": [ 1659794074.17, "5" ] }"#;
+        let lines = ndjson(scalar)?;
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["value"], 5.0);
+        assert!(lines[0].get("metric").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stitch_matrices() -> Result<(), anyhow::Error> {
+        let first = r#"
+[
+  { "metric": { "job": "a" }, "values": [ [ 0, "1" ], [ 30, "2" ], [ 60, "3" ] ] },
+  { "metric": { "job": "b" }, "values": [ [ 30, "1" ] ] }
+]
+"#;
+        let second = r#"
+[
+  { "metric": { "job": "c" }, "values": [ [ 90, "5" ] ] },
+  { "metric": { "job": "a" }, "values": [ [ 60, "3" ], [ 90, "4" ], [ 120, "5" ] ] }
+]
+"#;
+        let first = serde_json::from_str::<Vec<RangeVector>>(first)?;
+        let second = serde_json::from_str::<Vec<RangeVector>>(second)?;
+        let stitched = stitch_matrices(vec![first, second]);
+        let jobs: Vec<&str> = stitched
+            .iter()
+            .map(|s| s.metric().get("job").unwrap())
+            .collect();
+        assert_eq!(jobs, vec!["a", "b", "c"]);
+        let timestamps: Vec<f64> = stitched[0]
+            .samples()
+            .iter()
+            .map(|s| s.timestamp())
+            .collect();
+        assert_eq!(timestamps, vec![0.0, 30.0, 60.0, 90.0, 120.0]);
+        assert_eq!(stitched[1].samples().len(), 1);
+        assert_eq!(stitched[2].samples().len(), 1);
+        assert!(stitch_matrices(vec![]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_hash() -> Result<(), anyhow::Error> {
+        let first = serde_json::from_str::<PromqlResult>(
+            r#"{"resultType":"vector","result":[
+                {"metric":{"__name__":"up","job":"node"},"value":[1435781451.781,"1"]},
+                {"metric":{"__name__":"up","job":"prometheus"},"value":[1435781451.781,"0"]}
+            ]}"#,
+        )?;
+        // Same series in a different order, evaluated at a different time.
+        let reordered = serde_json::from_str::<PromqlResult>(
+            r#"{"resultType":"vector","result":[
+                {"metric":{"job":"prometheus","__name__":"up"},"value":[1435781466.781,"0"]},
+                {"metric":{"__name__":"up","job":"node"},"value":[1435781466.781,"1"]}
+            ]}"#,
+        )?;
+        assert_eq!(first.content_hash(), reordered.content_hash());
+
+        let changed = serde_json::from_str::<PromqlResult>(
+            r#"{"resultType":"vector","result":[
+                {"metric":{"__name__":"up","job":"node"},"value":[1435781451.781,"1"]},
+                {"metric":{"__name__":"up","job":"prometheus"},"value":[1435781451.781,"1"]}
+            ]}"#,
+        )?;
+        assert_ne!(first.content_hash(), changed.content_hash());
+
+        let relabeled = serde_json::from_str::<PromqlResult>(
+            r#"{"resultType":"vector","result":[
+                {"metric":{"__name__":"up","job":"node"},"value":[1435781451.781,"1"]},
+                {"metric":{"__name__":"up","job":"prometheu"},"value":[1435781451.781,"0"]}
+            ]}"#,
+        )?;
+        assert_ne!(first.content_hash(), relabeled.content_hash());
+
+        // The hash must be stable across program runs.
+        let empty = serde_json::from_str::<PromqlResult>(r#"{"resultType":"vector","result":[]}"#)?;
+        assert_eq!(empty.content_hash(), 0xe9628acaca9c7d82);
+
+        let matrix = r#"{"resultType":"matrix","result":[
+            {"metric":{"__name__":"up"},"values":[[1435781430,"1"],[1435781445,"1"]]}
+        ]}"#;
+        let matrix = serde_json::from_str::<PromqlResult>(matrix)?;
+        let shifted = r#"{"resultType":"matrix","result":[
+            {"metric":{"__name__":"up"},"values":[[1435781445,"1"],[1435781460,"1"]]}
+        ]}"#;
+        let shifted = serde_json::from_str::<PromqlResult>(shifted)?;
+        assert_ne!(matrix.content_hash(), shifted.content_hash());
+        assert_eq!(matrix.content_hash(), matrix.clone().content_hash());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_unix_millis() -> Result<(), anyhow::Error> {
+        let sample = serde_json::from_str::<Sample>(r#"[ 1435781451.781, "1" ]"#)?;
+        assert_eq!(sample.unix_millis(), 1435781451781);
+        let rfc3339 = serde_json::from_str::<Timestamp>(r#""2015-07-01T20:10:51.781Z""#)?;
+        assert_eq!(sample.as_timestamp(), rfc3339);
+        let sample = serde_json::from_str::<Sample>(r#"[ 1435781451, "1" ]"#)?;
+        assert_eq!(sample.unix_millis(), 1435781451000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_value_as_datetime() -> Result<(), anyhow::Error> {
+        let sample = serde_json::from_str::<Sample>(r#"[ 1700000000, "1699999200.5" ]"#)?;
+        let offset = UtcOffset::from_hms(-5, 0, 0)?;
+        let local = sample.value_as_datetime(offset).unwrap();
+        assert_eq!(local, datetime!(2023-11-14 17:00:00.5 -5));
+        assert_eq!(local.offset(), offset);
+        assert_eq!(local.hour(), 17);
+
+        for value in ["NaN", "+Inf", "1e30"] {
+            let data = format!(r#"[ 1700000000, "{}" ]"#, value);
+            let sample = serde_json::from_str::<Sample>(&data)?;
+            assert_eq!(sample.value_as_datetime(UtcOffset::UTC), None);
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_instant_vector_deserialization() -> Result<(), anyhow::Error> {
         let data = r#"
@@ -1540,6 +2979,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_series_deserialization() -> Result<(), anyhow::Error> {
+        let data = r#"
+[
+  {
+    "job": "prometheus",
+    "__name__": "up",
+    "instance": "localhost:9090"
+  },
+  {
+    "__name__": "process_start_time_seconds",
+    "job": "prometheus",
+    "instance": "localhost:9090"
+  }
+]
+"#;
+        let series = serde_json::from_str::<Vec<Metric>>(data)?;
+        assert!(series.len() == 2);
+        let first = &series[0];
+        assert!(first.name() == Some("up"));
+        assert!(first.get("job") == Some("prometheus"));
+        assert!(first.get("instance") == Some("localhost:9090"));
+        assert!(first.get("env").is_none());
+        let names = first.iter().map(|(k, _)| k.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["__name__", "instance", "job"]);
+        assert!(series[1].name() == Some("process_start_time_seconds"));
+        Ok(())
+    }
+
     #[test]
     fn test_target_deserialization() -> Result<(), anyhow::Error> {
         let data = r#"
@@ -1614,6 +3082,7 @@ mod tests {
         assert!(target.global_url() == &Url::parse("http://example-prometheus:9090/metrics")?);
         assert!(target.last_error().is_empty());
         assert!(target.last_scrape() == &datetime!(2017-01-17 15:07:44.723715405 +1));
+        assert_eq!(target.last_scrape_timestamp().unix_millis(), 1484662064723);
         assert!(target.last_scrape_duration() == 0.050688943);
         assert!(target.health().is_up());
         assert!(target.scrape_interval() == &Duration::seconds(60));
@@ -1731,6 +3200,7 @@ mod tests {
         assert!(alert.value() == 1.0);
         assert!(alert.state().is_firing());
         assert!(alert.active_at() == &datetime!(2018-07-04 20:27:12.60602144 +2));
+        assert_eq!(alert.active_at_timestamp().unix_millis(), 1530728832606);
         let recording_rule = &group.rules[1].as_recording().unwrap();
         assert!(recording_rule.health() == RuleHealth::Good);
         assert!(recording_rule.name() == "job:http_inprogress_requests:sum");
@@ -1762,6 +3232,51 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_pending_alert_deserialization() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "alerts": [
+    {
+      "activeAt": "2023-10-05T19:45:25.462004334+02:00",
+      "annotations": {
+        "summary": "High request latency",
+        "runbook_url": "https://example.com/runbooks/latency"
+      },
+      "labels": {
+        "alertname": "HighRequestLatency",
+        "job": "myjob",
+        "severity": "page"
+      },
+      "state": "pending",
+      "value": "6.4e-01"
+    }
+  ]
+}
+"#;
+        let alerts = serde_json::from_str::<Alerts>(data)?.alerts;
+        assert_eq!(alerts.len(), 1);
+        let alert = &alerts[0];
+        assert!(alert.state().is_pending());
+        assert_eq!(alert.value(), 0.64);
+        assert_eq!(alert.labels().name(), None);
+        assert_eq!(alert.labels().get("alertname"), Some("HighRequestLatency"));
+        assert_eq!(alert.labels().len(), 3);
+        assert_eq!(
+            alert.annotations().get("summary").map(String::as_str),
+            Some("High request latency")
+        );
+        assert_eq!(alert.annotations().len(), 2);
+        let active_at = datetime!(2023-10-05 7:45:25.462004334 pm +2);
+        assert_eq!(alert.active_at(), &active_at);
+        let evaluated_at = datetime!(2023-10-05 7:51:25.462004334 pm +2);
+        assert_eq!(
+            evaluated_at - *alert.active_at(),
+            time::Duration::minutes(6)
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_target_metadata_deserialization_1() -> Result<(), anyhow::Error> {
         let data = r#"
@@ -1978,6 +3493,29 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_notifications_deserialization() -> Result<(), anyhow::Error> {
+        let data = r#"
+[
+  {
+    "text": "Configuration reload has failed.",
+    "date": "2024-10-07T12:33:08.551376578+02:00",
+    "active": true
+  }
+]
+"#;
+        let notifications = serde_json::from_str::<Vec<Notification>>(data)?;
+        assert_eq!(notifications.len(), 1);
+        let notification = &notifications[0];
+        assert_eq!(notification.text(), "Configuration reload has failed.");
+        assert_eq!(
+            notification.date(),
+            &datetime!(2024-10-07 12:33:08.551376578 +2)
+        );
+        assert!(notification.active());
+        Ok(())
+    }
+
     #[test]
     fn test_buildinformation_deserialization() -> Result<(), anyhow::Error> {
         let data = r#"
@@ -2067,7 +3605,56 @@ mod tests {
   ]
 }
 "#;
-        serde_json::from_str::<TsdbStatistics>(data)?;
+        let stats = serde_json::from_str::<TsdbStatistics>(data)?;
+        let head = stats.head_stats();
+        assert_eq!(head.num_series(), 508);
+        assert_eq!(head.num_label_pairs(), None);
+        assert_eq!(head.chunk_count(), 937);
+        assert_eq!(head.min_time(), 1591516800000);
+        assert_eq!(head.max_time(), 1598896800143);
+        Ok(())
+    }
+
+    #[test]
+    fn test_head_stats_deserialization() -> Result<(), anyhow::Error> {
+        let data = r#"
+{
+  "numSeries": 9342,
+  "numLabelPairs": 1841,
+  "chunkCount": 37525,
+  "minTime": 1700000000000,
+  "maxTime": 1700007200500
+}
+"#;
+        let head = serde_json::from_str::<HeadStatistics>(data)?;
+        assert_eq!(head.num_series(), 9342);
+        assert_eq!(head.num_label_pairs(), Some(1841));
+        assert_eq!(head.chunk_count(), 37525);
+        assert_eq!(head.min_time(), 1700000000000);
+        assert_eq!(head.max_time(), 1700007200500);
+        assert_eq!(
+            head.min_time_as_datetime(),
+            Some(datetime!(2023-11-14 22:13:20 UTC))
+        );
+        assert_eq!(
+            head.max_time_as_datetime(),
+            Some(datetime!(2023-11-15 00:13:20.5 UTC))
+        );
+        assert_eq!(head.time_span(), Some(Duration::milliseconds(7200500)));
+
+        // An empty head block reports placeholder timestamps.
+        let data = r#"
+{
+  "numSeries": 0,
+  "chunkCount": 0,
+  "minTime": 9223372036854775807,
+  "maxTime": -9223372036854775808
+}
+"#;
+        let head = serde_json::from_str::<HeadStatistics>(data)?;
+        assert_eq!(head.time_span(), None);
+        assert_eq!(head.min_time_as_datetime(), None);
+        assert_eq!(head.max_time_as_datetime(), None);
         Ok(())
     }
 
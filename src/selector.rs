@@ -1,11 +1,17 @@
+use crate::error::{Error, InvalidFunctionArgumentError};
 use crate::util::*;
+use std::borrow::Cow;
 use std::fmt;
 
 /// A time series selector that is gradually built from a metric name and/or
 /// a set of label matchers.
+///
+/// Label values are escaped when the selector is rendered (see [`escape_label_value`]),
+/// so they must be passed unescaped.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Selector<'a> {
     pub(crate) labels: Vec<Label<'a>>,
+    pub(crate) at: Option<AtModifier>,
 }
 
 impl<'a> Default for Selector<'a> {
@@ -17,11 +23,19 @@ impl<'a> Default for Selector<'a> {
 impl<'a> Selector<'a> {
     /// Create a new instance of [Selector].
     pub fn new() -> Self {
-        Selector { labels: vec![] }
+        Selector {
+            labels: vec![],
+            at: None,
+        }
     }
 
     /// Select a metric name for this [Selector].
     ///
+    /// The metric name is not validated. Since it is rendered as a `__name__` label
+    /// matcher, names that collide with PromQL keywords (e.g. `sum` or `bool`) or
+    /// that contain characters outside of the classic metric name charset are
+    /// selected as well.
+    ///
     /// ```rust
     /// use prometheus_http_query::Selector;
     ///
@@ -32,14 +46,41 @@ impl<'a> Selector<'a> {
     ///
     /// assert_eq!(select, other_select);
     /// ```
-    pub fn metric(mut self, metric: &'a str) -> Self
+    pub fn metric(mut self, metric: impl Into<Cow<'a, str>>) -> Self
     where
         Self: Sized,
     {
-        self.labels.push(Label::Equal(("__name__", metric)));
+        self.labels.push(Label::Equal(("__name__", metric.into())));
         self
     }
 
+    /// Select all metrics whose name regex-matches the provided pattern for this [Selector],
+    /// e.g. to select multiple related metrics at once.<br>
+    /// PromQL equivalent: `{__name__=~"node_cpu.*"}`
+    ///
+    /// Like [`Selector::regex_eq`] the pattern is implicitly anchored by Prometheus and it
+    /// is not validated. A pattern that does not match the empty string (such as
+    /// `node_cpu.*`) may be used on its own to build a valid [Selector].
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Selector;
+    ///
+    /// let select = Selector::new()
+    ///     .metric_regex("node_cpu.*")
+    ///     .eq("mode", "idle")
+    ///     .to_string();
+    ///
+    /// let expected = r#"{__name__=~"node_cpu.*",mode="idle"}"#.to_string();
+    ///
+    /// assert_eq!(select, expected);
+    /// ```
+    pub fn metric_regex(self, pattern: impl Into<Cow<'a, str>>) -> Self
+    where
+        Self: Sized,
+    {
+        self.regex_eq("__name__", pattern)
+    }
+
     /// Append a label matcher to the set of matchers of [Selector] that
     /// selects labels that match the provided string.<br>
     /// PromQL equivalent: `http_requests_total{job="apiserver"}`
@@ -56,11 +97,11 @@ impl<'a> Selector<'a> {
     ///
     /// assert_eq!(select, expected);
     /// ```
-    pub fn eq(mut self, label: &'a str, value: &'a str) -> Self
+    pub fn eq(mut self, label: &'a str, value: impl Into<Cow<'a, str>>) -> Self
     where
         Self: Sized,
     {
-        self.labels.push(Label::Equal((label, value)));
+        self.labels.push(Label::Equal((label, value.into())));
         self
     }
 
@@ -80,11 +121,11 @@ impl<'a> Selector<'a> {
     ///
     /// assert_eq!(select, expected);
     /// ```
-    pub fn ne(mut self, label: &'a str, value: &'a str) -> Self
+    pub fn ne(mut self, label: &'a str, value: impl Into<Cow<'a, str>>) -> Self
     where
         Self: Sized,
     {
-        self.labels.push(Label::NotEqual((label, value)));
+        self.labels.push(Label::NotEqual((label, value.into())));
         self
     }
 
@@ -92,6 +133,11 @@ impl<'a> Selector<'a> {
     /// selects labels that regex-match the provided string.
     /// PromQL equivalent: `http_requests_total{job=~"apiserver"}`
     ///
+    /// Note that Prometheus implicitly anchors regular expressions, i.e. the
+    /// pattern must match the whole label value. `api` does not match the label
+    /// value `apiserver`, and adding `^` or `$` to the pattern is redundant.
+    /// Use [`Selector::regex_eq_partial`] to match a part of the label value.
+    ///
     /// ```rust
     /// use prometheus_http_query::Selector;
     ///
@@ -104,11 +150,73 @@ impl<'a> Selector<'a> {
     ///
     /// assert_eq!(select, expected);
     /// ```
-    pub fn regex_eq(mut self, label: &'a str, value: &'a str) -> Self
+    pub fn regex_eq(mut self, label: &'a str, value: impl Into<Cow<'a, str>>) -> Self
     where
         Self: Sized,
     {
-        self.labels.push(Label::RegexEqual((label, value)));
+        self.labels.push(Label::RegexEqual((label, value.into())));
+        self
+    }
+
+    /// Append a label matcher to the set of matchers of [Selector] that
+    /// selects labels that contain a match of the provided regular expression
+    /// anywhere in their value.<br>
+    /// PromQL equivalent: `http_requests_total{job=~".*api.*"}`
+    ///
+    /// As Prometheus implicitly anchors regular expressions (see [`Selector::regex_eq`]),
+    /// the pattern is grouped and wrapped in `.*` on both sides, i.e. `a|b` becomes
+    /// `.*(?:a|b).*`. A leading `^` or trailing `$` that anchors the whole pattern
+    /// is interpreted as an explicit anchor at the beginning or end of the label value
+    /// respectively, in which case the corresponding `.*` is omitted. Anchors that only
+    /// apply to a single alternative, as in `^a|b`, are kept within the group.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Selector;
+    ///
+    /// let select = Selector::new()
+    ///     .metric("http_requests_total")
+    ///     .regex_eq_partial("job", "api")
+    ///     .to_string();
+    ///
+    /// let expected = r#"{__name__="http_requests_total",job=~".*(?:api).*"}"#.to_string();
+    ///
+    /// assert_eq!(select, expected);
+    ///
+    /// // Only match label values that start with "api".
+    /// let select = Selector::new()
+    ///     .metric("http_requests_total")
+    ///     .regex_eq_partial("job", "^api")
+    ///     .to_string();
+    ///
+    /// let expected = r#"{__name__="http_requests_total",job=~"(?:api).*"}"#.to_string();
+    ///
+    /// assert_eq!(select, expected);
+    /// ```
+    pub fn regex_eq_partial(mut self, label: &'a str, pattern: impl AsRef<str>) -> Self
+    where
+        Self: Sized,
+    {
+        let pattern = pattern.as_ref();
+        let (mut prefix, mut suffix) = (".*", ".*");
+        let mut inner = pattern;
+        // Anchors may only be stripped if they apply to the whole pattern.
+        if !has_top_level_alternation(pattern) {
+            if let Some(p) = inner.strip_prefix('^') {
+                prefix = "";
+                inner = p;
+            }
+            if let Some(p) = inner.strip_suffix('$') {
+                // An odd number of preceding backslashes escapes the `$`.
+                let escapes = p.chars().rev().take_while(|c| *c == '\\').count();
+                if escapes % 2 == 0 {
+                    suffix = "";
+                    inner = p;
+                }
+            }
+        }
+        let value = format!("{}(?:{}){}", prefix, inner, suffix);
+        self.labels
+            .push(Label::RegexEqual((label, Cow::Owned(value))));
         self
     }
 
@@ -128,17 +236,148 @@ impl<'a> Selector<'a> {
     ///
     /// assert_eq!(select, expected);
     /// ```
-    pub fn regex_ne(mut self, label: &'a str, value: &'a str) -> Self
+    pub fn regex_ne(mut self, label: &'a str, value: impl Into<Cow<'a, str>>) -> Self
     where
         Self: Sized,
     {
-        self.labels.push(Label::RegexNotEqual((label, value)));
+        self.labels
+            .push(Label::RegexNotEqual((label, value.into())));
         self
     }
-}
 
-impl<'a> fmt::Display for Selector<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+    /// Append a label matcher to the set of matchers of [Selector] that
+    /// selects time series that have the given label set to any non-empty value.<br>
+    /// PromQL equivalent: `http_requests_total{job!=""}`
+    ///
+    /// Since this matcher does not match the empty string it may be used on
+    /// its own to build a valid [Selector].
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Selector;
+    ///
+    /// let select = Selector::new()
+    ///     .metric("http_requests_total")
+    ///     .has_label("job")
+    ///     .to_string();
+    ///
+    /// let expected = r#"{__name__="http_requests_total",job!=""}"#.to_string();
+    ///
+    /// assert_eq!(select, expected);
+    /// ```
+    pub fn has_label(self, label: &'a str) -> Self
+    where
+        Self: Sized,
+    {
+        self.ne(label, "")
+    }
+
+    /// Append a label matcher to the set of matchers of [Selector] that
+    /// selects time series that do not have the given label.<br>
+    /// PromQL equivalent: `http_requests_total{job=""}`
+    ///
+    /// Note that this matcher matches the empty string, so Prometheus rejects
+    /// a [Selector] that consists of this matcher alone.
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Selector;
+    ///
+    /// let select = Selector::new()
+    ///     .metric("http_requests_total")
+    ///     .lacks_label("job")
+    ///     .to_string();
+    ///
+    /// let expected = r#"{__name__="http_requests_total",job=""}"#.to_string();
+    ///
+    /// assert_eq!(select, expected);
+    /// ```
+    pub fn lacks_label(self, label: &'a str) -> Self
+    where
+        Self: Sized,
+    {
+        self.eq(label, "")
+    }
+
+    /// Set the evaluation time of the time series selected by this [Selector] to
+    /// the given point in time using the `@` modifier. Accepts any type that converts
+    /// into a [Timestamp], i.e. a Unix timestamp in seconds, a [`SystemTime`](std::time::SystemTime)
+    /// or an [`OffsetDateTime`](time::OffsetDateTime).<br>
+    /// PromQL equivalent: `http_requests_total{job="apiserver"} @ 1609746000`
+    ///
+    /// Note that Prometheus does not accept the `@` modifier in the series selectors
+    /// of metadata queries such as [`Client::series`](crate::Client::series).
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Selector;
+    ///
+    /// let select = Selector::new()
+    ///     .metric("http_requests_total")
+    ///     .at_timestamp(1609746000)
+    ///     .to_string();
+    ///
+    /// let expected = r#"{__name__="http_requests_total"} @ 1609746000"#.to_string();
+    ///
+    /// assert_eq!(select, expected);
+    /// ```
+    pub fn at_timestamp(mut self, timestamp: impl Into<Timestamp>) -> Self
+    where
+        Self: Sized,
+    {
+        self.at = Some(AtModifier::Timestamp(timestamp.into()));
+        self
+    }
+
+    /// Evaluate the time series selected by this [Selector] at the start of the
+    /// range query using the `@` modifier. In instant queries the start and end
+    /// of the query are both equal to the evaluation time.<br>
+    /// PromQL equivalent: `http_requests_total{job="apiserver"} @ start()`
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Selector;
+    ///
+    /// let select = Selector::new()
+    ///     .metric("http_requests_total")
+    ///     .at_start()
+    ///     .to_string();
+    ///
+    /// let expected = r#"{__name__="http_requests_total"} @ start()"#.to_string();
+    ///
+    /// assert_eq!(select, expected);
+    /// ```
+    pub fn at_start(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.at = Some(AtModifier::Start);
+        self
+    }
+
+    /// Evaluate the time series selected by this [Selector] at the end of the
+    /// range query using the `@` modifier. In instant queries the start and end
+    /// of the query are both equal to the evaluation time.<br>
+    /// PromQL equivalent: `http_requests_total{job="apiserver"} @ end()`
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Selector;
+    ///
+    /// let select = Selector::new()
+    ///     .metric("http_requests_total")
+    ///     .at_end()
+    ///     .to_string();
+    ///
+    /// let expected = r#"{__name__="http_requests_total"} @ end()"#.to_string();
+    ///
+    /// assert_eq!(select, expected);
+    /// ```
+    pub fn at_end(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.at = Some(AtModifier::End);
+        self
+    }
+
+    /// Render the label matchers of this [Selector] without any modifiers.
+    fn fmt_matchers(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         let matchers = self
             .labels
             .iter()
@@ -147,6 +386,165 @@ impl<'a> fmt::Display for Selector<'a> {
 
         write!(f, "{{{}}}", matchers.as_slice().join(","))
     }
+
+    /// Render this [Selector] as a range vector selector, e.g. `{job="node"}[5m]`.
+    /// Returns an error if `window` is not a valid PromQL duration.
+    fn range(&self, window: &str) -> Result<String, Error> {
+        if !is_valid_duration(window) {
+            return Err(Error::InvalidFunctionArgument(
+                InvalidFunctionArgumentError {
+                    message: "the time window of a range vector selector must be a PromQL duration greater than zero, e.g. \"5m\"",
+                },
+            ));
+        }
+        let range = RangeSelector {
+            selector: self,
+            window,
+        };
+        Ok(range.to_string())
+    }
+
+    fn over_time(&self, function: &str, window: &str) -> Result<String, Error> {
+        self.range(window).map(|r| format!("{}({})", function, r))
+    }
+
+    /// Apply `avg_over_time` to the time series selected by this [Selector] in the
+    /// given time `window` (a PromQL duration like `5m` or `1h30m`). The result is
+    /// the rendered PromQL expression that may be passed to [`Client::query`](crate::Client::query).<br>
+    /// PromQL equivalent: `avg_over_time(http_requests_total{job="apiserver"}[5m])`
+    ///
+    /// ```rust
+    /// use prometheus_http_query::Selector;
+    ///
+    /// let query = Selector::new()
+    ///     .metric("http_requests_total")
+    ///     .eq("job", "apiserver")
+    ///     .avg_over_time("5m");
+    ///
+    /// let expected = r#"avg_over_time({__name__="http_requests_total",job="apiserver"}[5m])"#;
+    ///
+    /// assert_eq!(query.unwrap(), expected);
+    ///
+    /// // Invalid durations are rejected.
+    /// assert!(Selector::new().metric("up").avg_over_time("5 minutes").is_err());
+    /// ```
+    pub fn avg_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("avg_over_time", window.as_ref())
+    }
+
+    /// Apply `min_over_time` to the time series selected by this [Selector], i.e. compute
+    /// the minimum value of all samples in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn min_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("min_over_time", window.as_ref())
+    }
+
+    /// Apply `max_over_time` to the time series selected by this [Selector], i.e. compute
+    /// the maximum value of all samples in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn max_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("max_over_time", window.as_ref())
+    }
+
+    /// Apply `sum_over_time` to the time series selected by this [Selector], i.e. compute
+    /// the sum of all sample values in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn sum_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("sum_over_time", window.as_ref())
+    }
+
+    /// Apply `count_over_time` to the time series selected by this [Selector], i.e. compute
+    /// the count of all samples in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn count_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("count_over_time", window.as_ref())
+    }
+
+    /// Apply `stddev_over_time` to the time series selected by this [Selector], i.e. compute
+    /// the population standard deviation of all sample values in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn stddev_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("stddev_over_time", window.as_ref())
+    }
+
+    /// Apply `stdvar_over_time` to the time series selected by this [Selector], i.e. compute
+    /// the population standard variance of all sample values in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn stdvar_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("stdvar_over_time", window.as_ref())
+    }
+
+    /// Apply `last_over_time` to the time series selected by this [Selector], i.e. compute
+    /// the most recent sample value in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn last_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("last_over_time", window.as_ref())
+    }
+
+    /// Apply `present_over_time` to the time series selected by this [Selector], i.e. compute
+    /// the value 1 for any series that has samples in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn present_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("present_over_time", window.as_ref())
+    }
+
+    /// Apply `absent_over_time` to the time series selected by this [Selector], i.e. return
+    /// a 1-element vector with the value 1 if no series has samples in the given time window.
+    /// See [`Selector::avg_over_time`] for details.
+    pub fn absent_over_time(&self, window: impl AsRef<str>) -> Result<String, Error> {
+        self.over_time("absent_over_time", window.as_ref())
+    }
+}
+
+impl<'a> fmt::Display for Selector<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        self.fmt_matchers(f)?;
+        match &self.at {
+            Some(at) => write!(f, " {}", at),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A [Selector] rendered as a range vector selector. The time window precedes
+/// any modifiers, e.g. `{job="node"}[5m] @ start()`.
+struct RangeSelector<'s, 'a> {
+    selector: &'s Selector<'a>,
+    window: &'s str,
+}
+
+impl<'s, 'a> fmt::Display for RangeSelector<'s, 'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        self.selector.fmt_matchers(f)?;
+        write!(f, "[{}]", self.window)?;
+        match &self.selector.at {
+            Some(at) => write!(f, " {}", at),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Check whether a regular expression contains an alternation `|` outside of
+/// character classes and groups, i.e. one that splits the whole pattern.
+fn has_top_level_alternation(pattern: &str) -> bool {
+    let mut depth = 0usize;
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            ']' if in_class => in_class = false,
+            _ if in_class => {}
+            '[' => in_class = true,
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
 }
 
 #[cfg(test)]
@@ -158,16 +556,233 @@ mod tests {
     fn test_selector_display_impl() {
         let s = Selector {
             labels: vec![
-                Label::Equal(("__name__", "http_requests_total")),
-                Label::Equal(("handler", "/api/comments")),
-                Label::RegexEqual(("job", ".*server")),
-                Label::RegexNotEqual(("status", "4..")),
-                Label::NotEqual(("env", "test")),
+                Label::Equal(("__name__", "http_requests_total".into())),
+                Label::Equal(("handler", "/api/comments".into())),
+                Label::RegexEqual(("job", ".*server".into())),
+                Label::RegexNotEqual(("status", "4..".into())),
+                Label::NotEqual(("env", "test".into())),
             ],
+            at: None,
         };
 
         let result = String::from("{__name__=\"http_requests_total\",handler=\"/api/comments\",job=~\".*server\",status!~\"4..\",env!=\"test\"}");
 
         assert_eq!(s.to_string(), result);
     }
+
+    #[test]
+    fn test_selector_has_label() {
+        let s = Selector::new().has_label("job");
+        assert_eq!(s.to_string(), r#"{job!=""}"#);
+        assert_eq!(s, Selector::new().ne("job", ""));
+        // The matcher does not match the empty string, so it satisfies the
+        // "at least one non-empty matcher" rule on its own.
+        assert!(matches!(s.labels.as_slice(), [Label::NotEqual((_, v))] if v.is_empty()));
+    }
+
+    #[test]
+    fn test_selector_metric_regex() -> Result<(), anyhow::Error> {
+        let s = Selector::new().metric_regex("node_cpu.*");
+        assert_eq!(s.to_string(), r#"{__name__=~"node_cpu.*"}"#);
+        assert_eq!(s, Selector::new().regex_eq("__name__", "node_cpu.*"));
+        // The matcher is the only one and still yields a complete selector.
+        assert_eq!(
+            s.clone().count_over_time("5m")?,
+            r#"count_over_time({__name__=~"node_cpu.*"}[5m])"#
+        );
+        assert_eq!(
+            s.eq("mode", "idle").to_string(),
+            r#"{__name__=~"node_cpu.*",mode="idle"}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_selector_lacks_label() {
+        let s = Selector::new().metric("up").lacks_label("job");
+        assert_eq!(s.to_string(), r#"{__name__="up",job=""}"#);
+        assert_eq!(s, Selector::new().metric("up").eq("job", ""));
+    }
+
+    #[test]
+    fn test_selector_over_time_functions() {
+        let s = Selector::new().metric("up").eq("job", "node");
+        assert_eq!(
+            s.count_over_time("5m").unwrap(),
+            r#"count_over_time({__name__="up",job="node"}[5m])"#
+        );
+        assert_eq!(
+            s.max_over_time("1h30m").unwrap(),
+            r#"max_over_time({__name__="up",job="node"}[1h30m])"#
+        );
+        assert_eq!(
+            s.stddev_over_time("90s").unwrap(),
+            r#"stddev_over_time({__name__="up",job="node"}[90s])"#
+        );
+        assert_eq!(
+            s.last_over_time("500ms").unwrap(),
+            r#"last_over_time({__name__="up",job="node"}[500ms])"#
+        );
+        assert_eq!(
+            s.present_over_time("1d").unwrap(),
+            r#"present_over_time({__name__="up",job="node"}[1d])"#
+        );
+        assert_eq!(
+            s.absent_over_time("2h").unwrap(),
+            r#"absent_over_time({__name__="up",job="node"}[2h])"#
+        );
+    }
+
+    #[test]
+    fn test_selector_over_time_invalid_window() {
+        let s = Selector::new().metric("up");
+        for window in ["", "5", "0m", "5mm", "1m1h"] {
+            assert!(matches!(
+                s.avg_over_time(window),
+                Err(Error::InvalidFunctionArgument(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_selector_regex_eq_partial() {
+        let cases = [
+            ("api", r#"{job=~".*(?:api).*"}"#),
+            ("^api", r#"{job=~"(?:api).*"}"#),
+            ("server$", r#"{job=~".*(?:server)"}"#),
+            ("^apiserver$", r#"{job=~"(?:apiserver)"}"#),
+            ("price\\$", r#"{job=~".*(?:price\\$).*"}"#),
+            ("price\\\\$", r#"{job=~".*(?:price\\\\)"}"#),
+            ("a|b", r#"{job=~".*(?:a|b).*"}"#),
+            ("^a|b", r#"{job=~".*(?:^a|b).*"}"#),
+            ("a|b$", r#"{job=~".*(?:a|b$).*"}"#),
+            ("^(a|b)$", r#"{job=~"(?:(a|b))"}"#),
+            ("^[|]$", r#"{job=~"(?:[|])"}"#),
+            ("^a\\|b", r#"{job=~"(?:a\\|b).*"}"#),
+        ];
+        for (pattern, expected) in cases {
+            let s = Selector::new().regex_eq_partial("job", pattern);
+            assert_eq!(s.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_selector_accepts_string_like_values() {
+        let job = String::from("node");
+        let mode = Cow::Borrowed("idle");
+        let s = Selector::new()
+            .metric(String::from("up"))
+            .eq("job", &job)
+            .ne("mode", mode)
+            .regex_eq("instance", format!("{}.*", "host"))
+            .regex_ne("env", job.clone())
+            .regex_eq_partial("cpu", String::from("0"));
+        assert_eq!(
+            s.to_string(),
+            r#"{__name__="up",job="node",mode!="idle",instance=~"host.*",env!~"node",cpu=~".*(?:0).*"}"#
+        );
+        let window = String::from("5m");
+        assert_eq!(
+            s.avg_over_time(&window).unwrap(),
+            s.avg_over_time("5m").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_selector_metric_not_validated() {
+        for name in ["sum", "bool", "offset", "inf", "http.requests", "métrique"] {
+            assert_eq!(
+                Selector::new().metric(name).to_string(),
+                format!(r#"{{__name__="{}"}}"#, name)
+            );
+        }
+    }
+
+    #[test]
+    fn test_selector_escapes_label_values() {
+        let s = Selector::new()
+            .eq("quote", r#"say "hi""#)
+            .ne("path", r"C:\temp")
+            .regex_eq("re", r"\d+\.\d+")
+            .regex_ne("multi", "a\nb");
+        assert_eq!(
+            s.to_string(),
+            r#"{quote="say \"hi\"",path!="C:\\temp",re=~"\\d+\\.\\d+",multi!~"a\nb"}"#
+        );
+    }
+
+    #[test]
+    fn test_selector_regex_eq_keeps_anchors() {
+        let s = Selector::new().regex_eq("job", "^api.*$");
+        assert_eq!(s.to_string(), r#"{job=~"^api.*$"}"#);
+    }
+
+    #[test]
+    fn test_selector_at_modifier() {
+        let s = Selector::new().metric("up");
+        assert_eq!(
+            s.clone().at_timestamp(1609746000).to_string(),
+            r#"{__name__="up"} @ 1609746000"#
+        );
+        assert_eq!(
+            s.clone().at_start().to_string(),
+            r#"{__name__="up"} @ start()"#
+        );
+        assert_eq!(s.clone().at_end().to_string(), r#"{__name__="up"} @ end()"#);
+        // The last modifier wins.
+        assert_eq!(
+            s.at_start().at_end().to_string(),
+            r#"{__name__="up"} @ end()"#
+        );
+    }
+
+    #[test]
+    fn test_selector_at_timestamp_conversions() {
+        use std::time::{Duration, SystemTime};
+
+        let s = Selector::new().metric("up");
+        let expected = r#"{__name__="up"} @ 1609459200"#;
+        assert_eq!(s.clone().at_timestamp(1609459200).to_string(), expected);
+        let system = SystemTime::UNIX_EPOCH + Duration::from_secs(1609459200);
+        assert_eq!(s.clone().at_timestamp(system).to_string(), expected);
+        let datetime = time::macros::datetime!(2021-01-01 00:00 UTC);
+        assert_eq!(s.clone().at_timestamp(datetime).to_string(), expected);
+        let timestamp = Timestamp::from(1609459200);
+        assert_eq!(s.clone().at_timestamp(timestamp).to_string(), expected);
+        // Fractions of a second are preserved with millisecond precision.
+        let system = SystemTime::UNIX_EPOCH + Duration::from_millis(1609459200250);
+        assert_eq!(
+            s.at_timestamp(system).to_string(),
+            r#"{__name__="up"} @ 1609459200.250"#
+        );
+    }
+
+    #[test]
+    fn test_selector_at_modifier_in_range_query() -> Result<(), anyhow::Error> {
+        let s = Selector::new()
+            .metric("http_requests_total")
+            .eq("job", "api");
+        assert_eq!(
+            s.clone().at_start().sum_over_time("5m")?,
+            r#"sum_over_time({__name__="http_requests_total",job="api"}[5m] @ start())"#
+        );
+        assert_eq!(
+            s.clone().at_end().max_over_time("1h")?,
+            r#"max_over_time({__name__="http_requests_total",job="api"}[1h] @ end())"#
+        );
+
+        let client = crate::Client::default();
+        let url = client
+            .query_range(s.at_start(), 1648373100, 1648373300, 10.0)
+            .build_url()?;
+        let query = url
+            .query_pairs()
+            .find(|(k, _)| k == "query")
+            .map(|(_, v)| v.into_owned());
+        assert_eq!(
+            query.as_deref(),
+            Some(r#"{__name__="http_requests_total",job="api"} @ start()"#)
+        );
+        Ok(())
+    }
 }
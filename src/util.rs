@@ -1,8 +1,13 @@
-use crate::error::{Error, ParseUrlError};
+use crate::error::{Error, InvalidQueryError, ParseUrlError};
 use mime::Mime;
 use reqwest::header::HeaderValue;
 use serde::Deserialize;
+use std::borrow::Cow;
 use std::fmt;
+use std::time::{Duration, SystemTime};
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime};
 use url::Url;
 
 /// A helper enum to filter targets by state.
@@ -136,7 +141,7 @@ pub enum RuleKind {
 }
 
 impl RuleKind {
-    pub(crate) fn to_query_param(&self) -> String {
+    pub(crate) fn to_query_param(self) -> String {
         match self {
             Self::Alerting => String::from("alert"),
             Self::Recording => String::from("record"),
@@ -156,19 +161,138 @@ impl fmt::Display for RuleKind {
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Label<'a> {
-    Equal((&'a str, &'a str)),
-    NotEqual((&'a str, &'a str)),
-    RegexEqual((&'a str, &'a str)),
-    RegexNotEqual((&'a str, &'a str)),
+    Equal((&'a str, Cow<'a, str>)),
+    NotEqual((&'a str, Cow<'a, str>)),
+    RegexEqual((&'a str, Cow<'a, str>)),
+    RegexNotEqual((&'a str, Cow<'a, str>)),
 }
 
 impl<'a> fmt::Display for Label<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
-            Self::Equal((k, v)) => write!(f, "{}=\"{}\"", k, v),
-            Self::NotEqual((k, v)) => write!(f, "{}!=\"{}\"", k, v),
-            Self::RegexEqual((k, v)) => write!(f, "{}=~\"{}\"", k, v),
-            Self::RegexNotEqual((k, v)) => write!(f, "{}!~\"{}\"", k, v),
+            Self::Equal((k, v)) => write!(f, "{}={}", k, quote(v)),
+            Self::NotEqual((k, v)) => write!(f, "{}!={}", k, quote(v)),
+            Self::RegexEqual((k, v)) => write!(f, "{}=~{}", k, quote(v)),
+            Self::RegexNotEqual((k, v)) => write!(f, "{}!~{}", k, quote(v)),
+        }
+    }
+}
+
+/// A point in time with millisecond precision, e.g. to anchor the evaluation time of a
+/// [`Selector`](crate::Selector) using [`Selector::at_timestamp`](crate::Selector::at_timestamp).<br>
+/// Conversions from an [`i64`] (Unix timestamp in seconds), a [`SystemTime`] and an
+/// [`OffsetDateTime`] are provided. The latter two truncate fractions of a millisecond
+/// towards the past.
+///
+/// Timestamps can also be deserialized from both formats used by the Prometheus API, i.e. from
+/// a Unix timestamp in (fractional) seconds as used by samples, or from an RFC 3339 string as
+/// used e.g. by targets and alerts. Fractional seconds are rounded to the nearest millisecond,
+/// as they merely carry floating point errors beyond the millisecond precision of Prometheus,
+/// while RFC 3339 strings are truncated like an [`OffsetDateTime`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    /// Create a [`Timestamp`] from a Unix timestamp in fractional seconds, rounded to the
+    /// nearest millisecond as Prometheus does not store timestamps more precisely.
+    pub(crate) fn from_secs_f64(seconds: f64) -> Self {
+        Timestamp((seconds * 1000.0).round() as i64)
+    }
+
+    /// Get the number of whole seconds since the Unix epoch.
+    pub fn unix_timestamp(&self) -> i64 {
+        self.0.div_euclid(1000)
+    }
+
+    /// Get the number of milliseconds since the Unix epoch.
+    pub fn unix_millis(&self) -> i64 {
+        self.0
+    }
+}
+
+impl From<i64> for Timestamp {
+    fn from(seconds: i64) -> Self {
+        Timestamp(seconds.saturating_mul(1000))
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(elapsed) => Timestamp(elapsed.as_millis() as i64),
+            Err(e) => {
+                let before = e.duration();
+                let millis =
+                    before.as_millis() as i64 + i64::from(before.subsec_nanos() % 1_000_000 > 0);
+                Timestamp(-millis)
+            }
+        }
+    }
+}
+
+impl From<OffsetDateTime> for Timestamp {
+    fn from(time: OffsetDateTime) -> Self {
+        Timestamp(time.unix_timestamp_nanos().div_euclid(1_000_000) as i64)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{Error as SerdeError, Unexpected};
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Number(f64),
+            Str(String),
+        }
+
+        match Value::deserialize(deserializer)? {
+            Value::Number(seconds) => Ok(Timestamp::from_secs_f64(seconds)),
+            Value::Str(s) => {
+                OffsetDateTime::parse(&s, &time::format_description::well_known::Rfc3339)
+                    .map(Timestamp::from)
+                    .map_err(|_| {
+                        SerdeError::invalid_value(
+                            Unexpected::Str(&s),
+                            &"an RFC 3339 date-time string",
+                        )
+                    })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    /// Render the timestamp as Unix timestamp in seconds, omitting the fraction of a
+    /// second if it is zero.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let millis = self.0.unsigned_abs();
+        match millis % 1000 {
+            0 => write!(f, "{}{}", sign, millis / 1000),
+            fraction => write!(f, "{}{}.{:03}", sign, millis / 1000, fraction),
+        }
+    }
+}
+
+/// The `@` modifier of a [`Selector`](crate::Selector) that sets the evaluation time.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum AtModifier {
+    Timestamp(Timestamp),
+    Start,
+    End,
+}
+
+impl fmt::Display for AtModifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::Timestamp(t) => write!(f, "@ {}", t),
+            Self::Start => f.write_str("@ start()"),
+            Self::End => f.write_str("@ end()"),
         }
     }
 }
@@ -183,23 +307,34 @@ pub(crate) trait ToBaseUrl {
 
 impl ToBaseUrl for &str {
     fn to_base_url(self) -> Result<Url, Error> {
-        Url::parse(self).map_err(|source| {
-            Error::ParseUrl(ParseUrlError {
-                message: "failed to build Prometheus server base URL",
-                source,
-            })
-        })
+        Url::parse(self)
+            .map_err(|source| {
+                Error::ParseUrl(ParseUrlError {
+                    message: "failed to build Prometheus server base URL",
+                    source,
+                })
+            })?
+            .to_base_url()
     }
 }
 
 impl ToBaseUrl for String {
     fn to_base_url(self) -> Result<Url, Error> {
-        Url::parse(&self).map_err(|source| {
-            Error::ParseUrl(ParseUrlError {
-                message: "failed to build Prometheus server base URL",
-                source,
-            })
-        })
+        self.as_str().to_base_url()
+    }
+}
+
+impl ToBaseUrl for Url {
+    fn to_base_url(self) -> Result<Url, Error> {
+        // Strings like "localhost:9090" are parsed successfully with "localhost" as the
+        // scheme, but no path segments can be appended to such URLs.
+        if self.cannot_be_a_base() {
+            return Err(Error::ParseUrl(ParseUrlError {
+                message: "failed to build Prometheus server base URL, the URL is missing a scheme like \"http://\"",
+                source: url::ParseError::RelativeUrlWithoutBase,
+            }));
+        }
+        Ok(self)
     }
 }
 
@@ -215,22 +350,255 @@ pub(crate) fn build_final_url(mut url: Url, path: &str) -> Url {
     url
 }
 
+// Compute the smallest step width (whole seconds, at least one second) for which a
+// range query from `start` to `end` yields at most `max_points` samples per series.
+pub(crate) fn fit_step(start: i64, end: i64, max_points: u64) -> u64 {
+    let window = end.saturating_sub(start).max(0) as u64;
+    match max_points {
+        0 | 1 => window + 1,
+        _ => window.div_ceil(max_points - 1).max(1),
+    }
+}
+
+// Split the time range of a range query into consecutive sub-ranges of at least `chunk`
+// seconds. The length of each sub-range is rounded up to a multiple of `step` so that the
+// samples of all sub-ranges lie on the same grid (this is exact for whole-second steps).
+// Consecutive sub-ranges share their boundary timestamp.
+pub(crate) fn split_range(start: i64, end: i64, step: f64, chunk: Duration) -> Vec<(i64, i64)> {
+    let steps = (chunk.as_secs_f64() / step).ceil().max(1.0);
+    let length = ((steps * step).ceil() as i64).max(1);
+    let mut ranges = vec![];
+    let mut from = start;
+    loop {
+        let to = from.saturating_add(length).min(end);
+        ranges.push((from, to));
+        if to >= end {
+            break ranges;
+        }
+        from = to;
+    }
+}
+
+/// Escape a label value according to the PromQL string literal rules, so that
+/// it can be safely placed between double quotes in a raw query string.
+///
+/// Backslashes, double quotes and newlines are escaped, everything else is
+/// left untouched. [`Selector`](crate::Selector) applies this to all
+/// label matcher values automatically.
+///
+/// ```rust
+/// use prometheus_http_query::escape_label_value;
+///
+/// assert_eq!(escape_label_value(r#"say "hi""#), r#"say \"hi\""#);
+/// assert_eq!(escape_label_value(r"C:\temp"), r"C:\\temp");
+///
+/// let query = format!(r#"up{{path="{}"}}"#, escape_label_value(r"C:\temp"));
+/// assert_eq!(query, r#"up{path="C:\\temp"}"#);
+/// ```
+pub fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Check a PromQL query for obvious syntax errors before sending it to the server, e.g. to
+/// give quick feedback in a query editor. This is not a full parser: it only detects empty
+/// queries, unbalanced parentheses, brackets and braces, unterminated string literals and
+/// queries that end with a binary operator. A query that passes may still be rejected by
+/// Prometheus.<br>
+/// Brackets within string literals and comments are ignored.
+///
+/// ```rust
+/// use prometheus_http_query::{validate_query, Error};
+///
+/// assert!(validate_query(r#"sum by (job) (rate(http_requests_total{code=~"5.."}[5m]))"#).is_ok());
+///
+/// match validate_query("sum(rate(http_requests_total[5m])") {
+///     Err(Error::InvalidQuery(e)) => {
+///         assert_eq!(e.message(), "unclosed parenthesis");
+///         assert_eq!(e.position(), 3);
+///     }
+///     _ => panic!("expected a syntax error"),
+/// }
+/// ```
+pub fn validate_query(query: &str) -> Result<(), Error> {
+    let invalid =
+        |message, position| Err(Error::InvalidQuery(InvalidQueryError { message, position }));
+
+    let mut open: Vec<(char, usize)> = vec![];
+    // The position of the last character that is not part of whitespace or a comment,
+    // and the start of the word it belongs to, if any.
+    let mut last: Option<(usize, char)> = None;
+    let mut word_start = 0;
+
+    let mut chars = query.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' | '`' => {
+                let mut terminated = false;
+                while let Some((_, s)) = chars.next() {
+                    if s == '\\' && c != '`' {
+                        chars.next();
+                    } else if s == c {
+                        terminated = true;
+                        break;
+                    }
+                }
+                if !terminated {
+                    return invalid("unterminated string literal", i);
+                }
+            }
+            '#' => {
+                while chars.next_if(|(_, s)| *s != '\n').is_some() {}
+                continue;
+            }
+            '(' | '[' | '{' => open.push((c, i)),
+            ')' | ']' | '}' => match open.pop() {
+                Some((o, _)) if matches!((o, c), ('(', ')') | ('[', ']') | ('{', '}')) => {}
+                Some((o, p)) => {
+                    return invalid(
+                        match o {
+                            '(' => "unclosed parenthesis",
+                            '[' => "unclosed bracket",
+                            _ => "unclosed brace",
+                        },
+                        p,
+                    )
+                }
+                None => {
+                    return invalid(
+                        match c {
+                            ')' => "unexpected closing parenthesis",
+                            ']' => "unexpected closing bracket",
+                            _ => "unexpected closing brace",
+                        },
+                        i,
+                    )
+                }
+            },
+            c if c.is_whitespace() => continue,
+            _ => {}
+        }
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        if is_word(c) && !last.is_some_and(|(p, l)| is_word(l) && p + l.len_utf8() == i) {
+            word_start = i;
+        }
+        last = Some((i, c));
+    }
+
+    if let Some((c, p)) = open.pop() {
+        return invalid(
+            match c {
+                '(' => "unclosed parenthesis",
+                '[' => "unclosed bracket",
+                _ => "unclosed brace",
+            },
+            p,
+        );
+    }
+
+    match last {
+        None => invalid("the query is empty", 0),
+        Some((i, c)) if "+-*/%^=<>!,".contains(c) => invalid("the query ends with an operator", i),
+        Some((i, c)) => {
+            let word = &query[word_start..i + c.len_utf8()];
+            match ["and", "or", "unless"]
+                .iter()
+                .any(|o| word.eq_ignore_ascii_case(o))
+            {
+                true => invalid("the query ends with an operator", word_start),
+                false => Ok(()),
+            }
+        }
+    }
+}
+
+// Render the given string as a double-quoted PromQL string literal.
+pub(crate) fn quote(value: &str) -> String {
+    format!("\"{}\"", escape_label_value(value))
+}
+
+// Check if the given string is a valid, non-zero PromQL duration like "5m" or "1h30m".
+// Units must be given in descending order and each unit may appear only once.
+pub(crate) fn is_valid_duration(duration: &str) -> bool {
+    const UNITS: [&str; 7] = ["y", "w", "d", "h", "m", "s", "ms"];
+
+    let mut rest = duration;
+    let mut last_unit = None;
+    let mut non_zero = false;
+
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return false;
+        }
+        let (num, tail) = rest.split_at(digits);
+        non_zero |= num.bytes().any(|b| b != b'0');
+
+        // Check "ms" before "m" and "s" as the latter are prefixes.
+        let unit = match UNITS
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, u)| tail.starts_with(*u))
+        {
+            Some((i, u)) => (i, *u),
+            None => return false,
+        };
+
+        if last_unit.is_some_and(|last| unit.0 <= last) {
+            return false;
+        }
+        last_unit = Some(unit.0);
+        rest = &tail[unit.1.len()..];
+    }
+
+    non_zero
+}
+
+// Parse the value of a `Retry-After` header, which is either a number of seconds
+// or an HTTP-date like "Wed, 21 Oct 2015 07:28:00 GMT". Dates in the past yield zero.
+pub(crate) fn parse_retry_after(v: &HeaderValue, now: OffsetDateTime) -> Option<Duration> {
+    const HTTP_DATE_FORMAT: &[FormatItem] = format_description!(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+    );
+
+    let value = v.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = PrimitiveDateTime::parse(value, &HTTP_DATE_FORMAT)
+        .ok()?
+        .assume_utc();
+    Some((date - now).try_into().unwrap_or(Duration::ZERO))
+}
+
 pub(crate) fn is_json(v: Option<&HeaderValue>) -> bool {
     match v
         .and_then(|h| h.to_str().ok())
         .and_then(|h| h.parse::<Mime>().ok())
     {
-        Some(mime) => match (mime.type_(), mime.subtype()) {
-            (mime::APPLICATION, mime::JSON) => true,
-            _ => false,
-        },
+        Some(mime) => matches!(
+            (mime.type_(), mime.subtype()),
+            (mime::APPLICATION, mime::JSON)
+        ),
         None => false,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{build_final_url, is_json, ToBaseUrl};
+    use super::{
+        build_final_url, fit_step, is_json, is_valid_duration, parse_retry_after, quote,
+        split_range, validate_query, Timestamp, ToBaseUrl,
+    };
 
     #[test]
     fn test_simple_str_to_url() {
@@ -290,4 +658,198 @@ mod tests {
         let header = reqwest::header::HeaderValue::from_static("application/json; charset=utf-8");
         assert!(is_json(Some(&header)));
     }
+
+    #[test]
+    fn test_valid_durations() {
+        for d in ["5m", "1h30m", "1y2w3d4h5m6s7ms", "500ms", "90s", "01m"] {
+            assert!(is_valid_duration(d), "{}", d);
+        }
+    }
+
+    #[test]
+    fn test_invalid_durations() {
+        for d in [
+            "", "5", "m", "0s", "5x", "30m1h", "5m5m", "1.5h", "-5m", "5m ",
+        ] {
+            assert!(!is_valid_duration(d), "{}", d);
+        }
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("job"), r#""job""#);
+        assert_eq!(quote(""), r#""""#);
+        assert_eq!(quote(r#"a "b" \d"#), r#""a \"b\" \\d""#);
+        assert_eq!(quote("line\nbreak"), r#""line\nbreak""#);
+    }
+
+    #[test]
+    fn test_fit_step() {
+        // 1 hour
+        assert_eq!(fit_step(0, 3600, 61), 60);
+        assert_eq!(fit_step(0, 3600, 60), 62);
+        assert_eq!(fit_step(0, 3600, 3601), 1);
+        // Never below one second.
+        assert_eq!(fit_step(0, 3600, 100_000), 1);
+        assert_eq!(fit_step(100, 100, 10), 1);
+        // 30 days
+        assert_eq!(fit_step(0, 2_592_000, 1000), 2595);
+        // A single point.
+        assert_eq!(fit_step(0, 300, 1), 301);
+        assert_eq!(fit_step(0, 300, 0), 301);
+
+        for window in [1, 59, 300, 3600, 86_400, 604_800, 2_592_000] {
+            for max_points in 1..=1200 {
+                let step = fit_step(1648373100, 1648373100 + window, max_points);
+                assert!(step >= 1);
+                let points = window as u64 / step + 1;
+                assert!(
+                    points <= max_points.max(1),
+                    "window={} max_points={} step={} points={}",
+                    window,
+                    max_points,
+                    step,
+                    points
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        use reqwest::header::HeaderValue;
+        use std::time::Duration;
+        use time::macros::datetime;
+
+        let now = datetime!(2015-10-21 07:27:30 UTC);
+        let parse = |v: &'static str| parse_retry_after(&HeaderValue::from_static(v), now);
+        assert_eq!(parse("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(parse("0"), Some(Duration::ZERO));
+        assert_eq!(
+            parse("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse("Wed, 21 Oct 2015 07:27:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse("-1"), None);
+        assert_eq!(parse("1.5"), None);
+        assert_eq!(parse("soon"), None);
+        assert_eq!(parse("Wed, 21 Oct 2015 07:28:00 CET"), None);
+    }
+
+    #[test]
+    fn test_split_range() {
+        use std::time::Duration;
+
+        let minute = Duration::from_secs(60);
+        assert_eq!(split_range(0, 120, 30.0, minute), vec![(0, 60), (60, 120)]);
+        assert_eq!(
+            split_range(0, 130, 30.0, minute),
+            vec![(0, 60), (60, 120), (120, 130)]
+        );
+        assert_eq!(split_range(0, 50, 30.0, minute), vec![(0, 50)]);
+        assert_eq!(split_range(100, 100, 30.0, minute), vec![(100, 100)]);
+        // The chunk length is rounded up to a multiple of the step width.
+        assert_eq!(
+            split_range(0, 200, 45.0, minute),
+            vec![(0, 90), (90, 180), (180, 200)]
+        );
+        // Chunks are never shorter than a single step.
+        assert_eq!(
+            split_range(0, 60, 30.0, Duration::ZERO),
+            vec![(0, 30), (30, 60)]
+        );
+    }
+
+    #[test]
+    fn test_timestamp_conversions() {
+        use std::time::{Duration, SystemTime};
+        use time::OffsetDateTime;
+
+        let expected = Timestamp(1609459200000);
+        assert_eq!(Timestamp::from(1609459200), expected);
+        let system = SystemTime::UNIX_EPOCH + Duration::from_secs(1609459200);
+        assert_eq!(Timestamp::from(system), expected);
+        let datetime = OffsetDateTime::from_unix_timestamp(1609459200).unwrap();
+        assert_eq!(Timestamp::from(datetime), expected);
+        assert_eq!(expected.unix_timestamp(), 1609459200);
+        assert_eq!(expected.unix_millis(), 1609459200000);
+
+        let system = SystemTime::UNIX_EPOCH + Duration::from_nanos(1609459200500900000);
+        assert_eq!(Timestamp::from(system).unix_millis(), 1609459200500);
+        assert_eq!(Timestamp::from(system).unix_timestamp(), 1609459200);
+
+        let before_epoch = SystemTime::UNIX_EPOCH - Duration::from_micros(1500500);
+        assert_eq!(Timestamp::from(before_epoch), Timestamp(-1501));
+        assert_eq!(Timestamp::from(before_epoch).unix_timestamp(), -2);
+        let before_epoch = SystemTime::UNIX_EPOCH - Duration::from_secs(2);
+        assert_eq!(Timestamp::from(before_epoch), Timestamp(-2000));
+    }
+
+    #[test]
+    fn test_timestamp_deserialization() -> Result<(), anyhow::Error> {
+        let seconds = serde_json::from_str::<Timestamp>("1435781451.781")?;
+        let rfc3339 = serde_json::from_str::<Timestamp>(r#""2015-07-01T20:10:51.781Z""#)?;
+        assert_eq!(seconds, rfc3339);
+        assert_eq!(seconds.unix_millis(), 1435781451781);
+
+        let rfc3339 = serde_json::from_str::<Timestamp>(r#""2015-07-01T22:10:51.781+02:00""#)?;
+        assert_eq!(seconds, rfc3339);
+        let whole = serde_json::from_str::<Timestamp>("1435781451")?;
+        assert_eq!(whole, Timestamp::from(1435781451));
+
+        assert!(serde_json::from_str::<Timestamp>(r#""yesterday""#).is_err());
+        assert!(serde_json::from_str::<Timestamp>("true").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp_display() {
+        assert_eq!(Timestamp::from(1609459200).to_string(), "1609459200");
+        assert_eq!(Timestamp(1609459200050).to_string(), "1609459200.050");
+        assert_eq!(Timestamp(-1500).to_string(), "-1.500");
+        assert_eq!(Timestamp(-2000).to_string(), "-2");
+    }
+
+    #[test]
+    fn test_validate_query() {
+        use crate::error::Error;
+
+        let error = |query: &str| match validate_query(query) {
+            Err(Error::InvalidQuery(e)) => (e.message, e.position),
+            _ => panic!("expected a syntax error for {:?}", query),
+        };
+
+        for query in [
+            "up",
+            r#"sum by (job) (rate(http_requests_total{code=~"5..", path!="/(health"}[5m]))"#,
+            "rate(up[5m:1m]) > 0 and on(job) up == 1",
+            "up # unbalanced ( in a comment\n+ 1",
+            "histogram_quantile(0.9, sum by (le) (rate(x_bucket[5m])))",
+            "-1",
+            "up offset -5m",
+            "sort_desc(operator)",
+            r#"label_replace(up, "x", "$1", "y", "(.*\")")"#,
+        ] {
+            assert!(validate_query(query).is_ok(), "{:?} is valid", query);
+        }
+
+        assert_eq!(error(""), ("the query is empty", 0));
+        assert_eq!(error("  \n\t"), ("the query is empty", 0));
+        assert_eq!(error("# only a comment"), ("the query is empty", 0));
+        assert_eq!(error("sum(rate(up[5m])"), ("unclosed parenthesis", 3));
+        assert_eq!(error("up[5m"), ("unclosed bracket", 2));
+        assert_eq!(error(r#"up{job="a""#), ("unclosed brace", 2));
+        assert_eq!(error("sum(up))"), ("unexpected closing parenthesis", 7));
+        assert_eq!(error("sum(up[5m)]"), ("unclosed bracket", 6));
+        assert_eq!(error("}"), ("unexpected closing brace", 0));
+        assert_eq!(error(r#"up{job="a}"#), ("unterminated string literal", 7));
+        assert_eq!(error("up +"), ("the query ends with an operator", 3));
+        assert_eq!(error("up >= "), ("the query ends with an operator", 4));
+        assert_eq!(error("up and"), ("the query ends with an operator", 3));
+        assert_eq!(
+            error("up OR # comment"),
+            ("the query ends with an operator", 3)
+        );
+    }
 }
@@ -0,0 +1,381 @@
+//! Deserialize recorded Prometheus API responses through the public client API.
+//!
+//! Every file in `tests/fixtures` is a complete response body (including the
+//! `status` envelope) as returned by the corresponding endpoint of a real
+//! Prometheus server.
+use mockito::{Matcher, Mock, ServerGuard};
+use prometheus_http_query::{Client, Selector};
+use std::time::Duration;
+
+async fn serve(server: &mut ServerGuard, method: &str, path: &str, body: &str) -> Mock {
+    server
+        .mock(method, path)
+        .match_query(Matcher::Any)
+        .with_header("content-type", "application/json")
+        .with_body(body)
+        .create_async()
+        .await
+}
+
+#[tokio::test]
+async fn test_query_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "GET",
+        "/api/v1/query",
+        include_str!("fixtures/query.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let result = client.query("up").get().await?;
+    let vector = result.data().as_vector().unwrap();
+    assert_eq!(vector.len(), 2);
+    assert_eq!(vector[0].metric().get("job"), Some("prometheus"));
+    assert_eq!(vector[0].sample().timestamp(), 1659599786.567);
+    assert_eq!(vector[0].sample().value(), 1.0);
+    assert_eq!(vector[1].metric().get("instance"), Some("localhost:9100"));
+    assert_eq!(vector[1].sample().value(), 0.0);
+    mock.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_range_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "GET",
+        "/api/v1/query_range",
+        include_str!("fixtures/query_range.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let result = client
+        .query_range("up", 1659599786, 1659599816, 15.0)
+        .get()
+        .await?;
+    let matrix = result.data().as_matrix().unwrap();
+    assert_eq!(matrix.len(), 2);
+    assert_eq!(matrix[0].metric().name(), Some("up"));
+    assert_eq!(matrix[0].samples().len(), 3);
+    assert_eq!(matrix[1].metric().get("job"), Some("node"));
+    assert_eq!(matrix[1].samples()[0].value(), 0.0);
+    assert_eq!(matrix[1].samples()[2].timestamp(), 1659599816.0);
+    mock.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_series_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "GET",
+        "/api/v1/series",
+        include_str!("fixtures/series.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let select = Selector::new().eq("job", "prometheus");
+    let series = client.series([&select])?.get().await?;
+    assert_eq!(series.len(), 2);
+    assert_eq!(series[0].name(), Some("up"));
+    assert_eq!(series[1].name(), Some("process_start_time_seconds"));
+    assert!(series.iter().all(|s| s.get("job") == Some("prometheus")));
+    mock.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_labels_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let names = serve(
+        &mut server,
+        "GET",
+        "/api/v1/labels",
+        include_str!("fixtures/labels.json"),
+    )
+    .await;
+    let values = serve(
+        &mut server,
+        "GET",
+        "/api/v1/label/job/values",
+        include_str!("fixtures/label_values.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let labels = client.label_names().get().await?;
+    assert_eq!(labels.len(), 21);
+    assert!(labels.iter().any(|l| l == "__name__"));
+    assert!(labels.iter().any(|l| l == "job"));
+    let jobs = client.label_values("job").get().await?;
+    assert_eq!(jobs, vec!["node", "prometheus"]);
+    names.assert_async().await;
+    values.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_targets_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "GET",
+        "/api/v1/targets",
+        include_str!("fixtures/targets.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let targets = client.targets(None).await?;
+    assert_eq!(targets.active().len(), 1);
+    let active = &targets.active()[0];
+    assert_eq!(active.scrape_pool(), "prometheus");
+    assert_eq!(
+        active.scrape_url().as_str(),
+        "http://127.0.0.1:9090/metrics"
+    );
+    assert!(active.health().is_up());
+    assert_eq!(*active.scrape_interval(), time::Duration::minutes(1));
+    assert_eq!(targets.dropped().len(), 1);
+    assert!(targets.dropped()[0]
+        .discovered_labels()
+        .get("job")
+        .is_some_and(|v| v == "node"));
+    mock.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rules_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "GET",
+        "/api/v1/rules",
+        include_str!("fixtures/rules.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let groups = client.rules().get().await?;
+    assert_eq!(groups.len(), 1);
+    let group = &groups[0];
+    assert_eq!(group.name(), "example");
+    assert_eq!(group.file(), "/rules.yaml");
+    assert_eq!(group.interval(), 60.0);
+    let alerting = group.rules()[0].as_alerting().unwrap();
+    assert_eq!(alerting.name(), "HighRequestLatency");
+    assert_eq!(alerting.duration(), 600.0);
+    assert_eq!(alerting.alerts().len(), 1);
+    let recording = group.rules()[1].as_recording().unwrap();
+    assert_eq!(recording.name(), "job:http_inprogress_requests:sum");
+    assert_eq!(recording.query(), "sum by (job) (http_inprogress_requests)");
+    mock.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_alerts_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "GET",
+        "/api/v1/alerts",
+        include_str!("fixtures/alerts.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let alerts = client.alerts().await?;
+    assert_eq!(alerts.len(), 1);
+    let alert = &alerts[0];
+    assert!(alert.state().is_firing());
+    assert_eq!(alert.labels().get("alertname"), Some("HighRequestLatency"));
+    assert_eq!(alert.value(), 1.0);
+    mock.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_metadata_fixtures() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let targets = serve(
+        &mut server,
+        "GET",
+        "/api/v1/targets/metadata",
+        include_str!("fixtures/targets_metadata.json"),
+    )
+    .await;
+    let metrics = serve(
+        &mut server,
+        "GET",
+        "/api/v1/metadata",
+        include_str!("fixtures/metadata.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let metadata = client.target_metadata().get().await?;
+    assert_eq!(metadata.len(), 2);
+    assert_eq!(metadata[0].metric(), Some("go_goroutines"));
+    assert!(metadata[0].metric_type().is_gauge());
+    assert!(metadata[1].metric_type().is_histogram());
+    assert!(metadata[1]
+        .target()
+        .get("instance")
+        .is_some_and(|v| v == "127.0.0.1:9091"));
+
+    let metadata = client.metric_metadata().get().await?;
+    assert_eq!(metadata.len(), 2);
+    let requests = &metadata["http_requests_total"];
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].metric_type().is_counter());
+    assert_eq!(requests[0].help(), "Number of HTTP requests");
+    targets.assert_async().await;
+    metrics.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_alertmanagers_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "GET",
+        "/api/v1/alertmanagers",
+        include_str!("fixtures/alertmanagers.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let alertmanagers = client.alertmanagers().await?;
+    assert_eq!(alertmanagers.active().len(), 1);
+    assert_eq!(
+        alertmanagers.active()[0].url().as_str(),
+        "http://127.0.0.1:9090/api/v1/alerts"
+    );
+    assert_eq!(alertmanagers.dropped().len(), 1);
+    mock.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_notifications_fixture() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "GET",
+        "/api/v1/notifications",
+        include_str!("fixtures/notifications.json"),
+    )
+    .await;
+
+    let client = Client::try_from(server.url())?;
+    let notifications = client.notifications().await?;
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].text(), "Configuration reload has failed.");
+    assert!(notifications[0].active());
+    mock.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_status_fixtures() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mocks = [
+        serve(
+            &mut server,
+            "GET",
+            "/api/v1/status/flags",
+            include_str!("fixtures/status_flags.json"),
+        )
+        .await,
+        serve(
+            &mut server,
+            "GET",
+            "/api/v1/status/buildinfo",
+            include_str!("fixtures/status_buildinfo.json"),
+        )
+        .await,
+        serve(
+            &mut server,
+            "GET",
+            "/api/v1/status/runtimeinfo",
+            include_str!("fixtures/status_runtimeinfo.json"),
+        )
+        .await,
+        serve(
+            &mut server,
+            "GET",
+            "/api/v1/status/tsdb",
+            include_str!("fixtures/status_tsdb.json"),
+        )
+        .await,
+        serve(
+            &mut server,
+            "GET",
+            "/api/v1/status/walreplay",
+            include_str!("fixtures/status_walreplay.json"),
+        )
+        .await,
+    ];
+
+    let client = Client::try_from(server.url())?;
+
+    let flags = client.flags().await?;
+    assert_eq!(flags.get("storage.tsdb.retention.time").unwrap(), "15d");
+
+    let build = client.build_information().await?;
+    assert_eq!(build.version(), "2.13.1");
+    assert_eq!(build.branch(), "master");
+    assert_eq!(build.go_version(), "go1.13.1");
+
+    let runtime = client.runtime_information().await?;
+    assert_eq!(runtime.cwd(), "/");
+    assert!(runtime.reload_config_success());
+    assert_eq!(runtime.goroutine_count(), 48);
+    assert_eq!(*runtime.storage_retention(), time::Duration::days(15));
+
+    let tsdb = client.tsdb_statistics().await?;
+    assert_eq!(tsdb.head_stats().num_series(), 508);
+    assert_eq!(tsdb.head_stats().chunk_count(), 937);
+    assert_eq!(tsdb.series_count_by_metric_name().len(), 2);
+    assert_eq!(tsdb.label_value_count_by_label_name()[0].name(), "__name__");
+    assert_eq!(tsdb.label_value_count_by_label_name()[0].value(), 211);
+
+    let wal = client.wal_replay_statistics().await?;
+    assert_eq!(wal.current(), wal.max());
+    assert!(wal.state().is_some_and(|s| s.is_done()));
+
+    for mock in mocks {
+        mock.assert_async().await;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fixtures_over_post() -> Result<(), anyhow::Error> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = serve(
+        &mut server,
+        "POST",
+        "/api/v1/query",
+        include_str!("fixtures/query.json"),
+    )
+    .await;
+
+    let client = Client::builder()
+        .base_url(server.url())
+        .pool_idle_timeout(Duration::from_secs(10))
+        .build()?;
+    let result = client.query("up").post().await?;
+    assert_eq!(result.data().as_vector().map(|v| v.len()), Some(2));
+    mock.assert_async().await;
+    Ok(())
+}
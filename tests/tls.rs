@@ -0,0 +1,41 @@
+//! Verify that the TLS backend selected via feature flags is usable.
+//!
+//! Run e.g. `cargo test --test tls --no-default-features --features rustls-tls`
+//! to check a particular backend.
+use prometheus_http_query::Client;
+
+#[cfg(any(
+    feature = "native-tls",
+    feature = "native-tls-vendored",
+    feature = "native-tls-alpn"
+))]
+#[test]
+fn test_native_tls_client() -> Result<(), anyhow::Error> {
+    let inner = reqwest::Client::builder().use_native_tls().build()?;
+    let client = Client::from(inner, "https://localhost:9090")?;
+    assert_eq!(client.base_url().scheme(), "https");
+    Ok(())
+}
+
+#[cfg(any(
+    feature = "rustls-tls",
+    feature = "rustls-tls-manual-roots",
+    feature = "rustls-tls-webpki-roots",
+    feature = "rustls-tls-native-roots"
+))]
+#[test]
+fn test_rustls_client() -> Result<(), anyhow::Error> {
+    let inner = reqwest::Client::builder().use_rustls_tls().build()?;
+    let client = Client::from(inner, "https://localhost:9090")?;
+    assert_eq!(client.base_url().scheme(), "https");
+    Ok(())
+}
+
+#[test]
+fn test_https_client() -> Result<(), anyhow::Error> {
+    let client = Client::builder()
+        .base_url("https://localhost:9090")
+        .build()?;
+    assert_eq!(client.base_url().scheme(), "https");
+    Ok(())
+}